@@ -1,18 +1,19 @@
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use documents::prelude::*;
 use react::prelude::*;
 use stdext::prelude::*;
 
+const SAVE: &str = "editor.save";
+
 pub fn app(file: Document) -> Component {
     let (textfield, buffer) = text_field(file.content().unwrap_or("".to_string()));
-    Widget::stateful(
+    let app = Widget::stateful(
         (textfield, buffer),
         move |this, msg| {
             switch(msg)
                 .case({
                     let mut file = file.clone();
-                    move |event: &KeyEvent| match (event.modifiers, event.code) {
-                        (KeyModifiers::CONTROL, KeyCode::Char('s')) => {
+                    move |&Action(action)| match action {
+                        SAVE => {
                             _ = file.replace_with(this.state.1.borrow().as_bytes());
                             Intercept
                         }
@@ -22,5 +23,6 @@ pub fn app(file: Document) -> Component {
                 .default(|| Propagate)
         },
         move |(textfield, _)| textfield.clone(),
-    )
+    );
+    with_keymap(app, Keymap::new().bind(SAVE, "Ctrl+S"))
 }
@@ -0,0 +1,366 @@
+use std::{f64::consts::PI, time::Duration};
+
+use stdext::prelude::switch;
+
+use crate::{
+    component::prelude::*,
+    displaylist::Vec2,
+    lifecycle::on_mount,
+    message::{bubble, prelude::*},
+    style::Color,
+    tick::Tick,
+    widget::Widget,
+};
+
+pub mod prelude {
+    pub use super::{Animation, AnimationDone, Easing, Lerp, Repeat, animate};
+}
+
+/// How often running animations are redrawn
+pub const FRAME: Duration = Duration::from_millis(16);
+
+/// Values that can be interpolated, `t` going from 0 at `self` to 1 at `to`
+pub trait Lerp {
+    fn lerp(&self, to: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, to: &Self, t: f64) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, to: &Self, t: f64) -> Self {
+        (*self as f64).lerp(&(*to as f64), t) as f32
+    }
+}
+
+macro_rules! lerp_rounded {
+    ($($t:ty),*) => {$(
+        impl Lerp for $t {
+            fn lerp(&self, to: &Self, t: f64) -> Self {
+                (*self as f64).lerp(&(*to as f64), t).round() as $t
+            }
+        }
+    )*};
+}
+
+lerp_rounded!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl Lerp for Vec2 {
+    fn lerp(&self, to: &Self, t: f64) -> Self {
+        Vec2 {
+            x: self.x.lerp(&to.x, t),
+            y: self.y.lerp(&to.y, t),
+        }
+    }
+}
+
+/// Blends through RGB, whatever kind of colors the ends are
+impl Lerp for Color {
+    fn lerp(&self, to: &Self, t: f64) -> Self {
+        let ((r1, g1, b1), (r2, g2, b2)) = (self.rgb(), to.rgb());
+        Color::Rgb(r1.lerp(&r2, t), g1.lerp(&g2, t), b1.lerp(&b2, t))
+    }
+}
+
+/// How progress through an animation maps onto the way from one value to the other
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Overshoots and settles, like a weight on a spring
+    Spring,
+}
+
+impl Easing {
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::EaseInOut => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+            Easing::Spring if t == 1.0 => 1.0,
+            Easing::Spring => 1.0 - (-6.0 * t).exp() * (3.0 * PI * t).cos(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Repeat {
+    #[default]
+    Once,
+    /// Plays this many times in all; none at all ends the animation before it starts
+    Times(u32),
+    Forever,
+}
+
+/// Sent up from an `animate` widget when its animation ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationDone(pub usize);
+
+/// A value moving from `from` to `to` over `duration`, timed by `Tick`s
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation<T> {
+    pub from: T,
+    pub to: T,
+    pub duration: Duration,
+    pub easing: Easing,
+    pub repeat: Repeat,
+    /// Plays every other round backwards
+    pub reverse: bool,
+    started: Option<Duration>,
+    elapsed: Duration,
+}
+
+impl<T: Lerp + Clone> Animation<T> {
+    pub fn new(from: T, to: T, duration: Duration) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            easing: Easing::default(),
+            repeat: Repeat::default(),
+            reverse: false,
+            started: None,
+            elapsed: Duration::ZERO,
+        }
+    }
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+    pub fn repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+    pub fn reverse(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+    /// Moves to `now`, the first call starting the animation
+    pub fn advance(&mut self, now: Duration) {
+        let started = *self.started.get_or_insert(now);
+        self.elapsed = now.saturating_sub(started);
+    }
+    /// Starts over from the next `advance`
+    pub fn restart(&mut self) {
+        self.started = None;
+        self.elapsed = Duration::ZERO;
+    }
+    fn rounds(&self) -> Option<u32> {
+        match self.repeat {
+            Repeat::Once => Some(1),
+            Repeat::Times(times) => Some(times),
+            Repeat::Forever => None,
+        }
+    }
+    pub fn is_running(&self) -> bool {
+        match self.rounds() {
+            Some(rounds) => self.elapsed < self.duration * rounds,
+            None => true,
+        }
+    }
+    /// Where the animation is within its current round, from 0 to 1 before easing
+    fn progress(&self) -> f64 {
+        if self.duration.is_zero() || self.rounds() == Some(0) {
+            return 1.0;
+        }
+        let played = self.elapsed.as_secs_f64() / self.duration.as_secs_f64();
+        let (round, t) = match self.rounds() {
+            Some(rounds) if played >= rounds as f64 => (rounds - 1, 1.0),
+            _ => (played.floor() as u32, played.fract()),
+        };
+        if self.reverse && round % 2 == 1 {
+            1.0 - t
+        } else {
+            t
+        }
+    }
+    pub fn value(&self) -> T {
+        self.from.lerp(&self.to, self.easing.apply(self.progress()))
+    }
+}
+
+/// Builds from the value of `animation`, asking for a frame every `FRAME` while it runs.
+/// Sends `AnimationDone` up when it ends, right on mount if it has nothing to play.
+/// Rebuilds of the parent leave it where it is.
+pub fn animate<T: Lerp + Clone + 'static>(
+    animation: Animation<T>,
+    builder: impl Fn(&T) -> Component + 'static,
+) -> Component {
+    let running = animation.is_running();
+    let component = Widget::stateful(
+        animation,
+        |this, msg| {
+            switch(msg).case(|&Tick(now)| {
+                this.set_state(|animation| animation.advance(now));
+                if !this.state.is_running() {
+                    this.set_tick_interval(None);
+                    bubble(this.id(), AnimationDone(this.id()));
+                }
+            });
+            Propagate
+        },
+        move |animation| builder(&animation.value()),
+    );
+    if running {
+        component.borrow_mut().set_tick_interval(Some(FRAME));
+        return component;
+    }
+    let id = component.borrow().id();
+    on_mount(component, move || bubble(id, AnimationDone(id)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::{Harness, Size, text};
+
+    struct Rebuild;
+
+    fn at<T: Lerp + Clone>(mut animation: Animation<T>, millis: &[u64]) -> Vec<T> {
+        animation.advance(Duration::ZERO);
+        millis
+            .iter()
+            .map(|&ms| {
+                animation.advance(Duration::from_millis(ms));
+                animation.value()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn easings_start_at_zero_and_end_at_one() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::Spring,
+        ] {
+            [easing.apply(0.0), easing.apply(1.0)].must_be([0.0, 1.0]);
+        }
+        (Easing::EaseIn.apply(0.5) < 0.5).must_be(true);
+        (Easing::EaseOut.apply(0.5) > 0.5).must_be(true);
+        (Easing::Spring.apply(0.4) > 1.0).must_be(true);
+    }
+
+    #[test]
+    fn animations_interpolate_and_stop_at_the_end() {
+        let animation = Animation::new(0, 100, Duration::from_millis(100));
+        at(animation, &[0, 25, 50, 100, 500]).must_be(vec![0, 25, 50, 100, 100]);
+    }
+
+    #[test]
+    fn repeats_can_play_backwards() {
+        let animation = Animation::new(0, 100, Duration::from_millis(100))
+            .repeat(Repeat::Times(2))
+            .reverse();
+        at(animation.clone(), &[50, 150, 190, 300]).must_be(vec![50, 50, 10, 0]);
+        let forever = animation.repeat(Repeat::Forever);
+        at(forever, &[250, 350]).must_be(vec![50, 50]);
+    }
+
+    #[test]
+    fn positions_and_colors_interpolate() {
+        Vec2 { x: 0, y: 10 }
+            .lerp(&Vec2 { x: 10, y: 0 }, 0.5)
+            .must_be(Vec2 { x: 5, y: 5 });
+        Color::Black
+            .lerp(&Color::Rgb(200, 100, 50), 0.5)
+            .must_be(Color::Rgb(100, 50, 25));
+        Color::Indexed(196).rgb().must_be((255, 0, 0));
+    }
+
+    #[test]
+    fn frames_are_only_scheduled_while_running() {
+        let done = Rc::new(RefCell::new(vec![]));
+        let finished = done.clone();
+        let mut harness = Harness::mount(
+            Widget::stateful(
+                (),
+                move |_, msg| {
+                    switch(msg).case(|&AnimationDone(id)| finished.borrow_mut().push(id));
+                    Propagate
+                },
+                |_| {
+                    animate(Animation::new(0, 10, Duration::from_millis(100)), |x| {
+                        text(x.to_string())
+                    })
+                },
+            ),
+            Size { x: 2, y: 1 },
+        );
+        crate::tick::next_due().must_be(Some(FRAME));
+        harness.tick(FRAME);
+        harness.tick(Duration::from_millis(50));
+        harness.lines().must_be(vec!["5"]);
+        harness.tick(Duration::from_millis(50));
+        harness.lines().must_be(vec!["10"]);
+        done.borrow().len().must_be(1);
+        crate::tick::next_due().must_be(None);
+    }
+
+    #[test]
+    fn animations_played_no_times_are_done_at_once() {
+        let done = Rc::new(RefCell::new(vec![]));
+        let finished = done.clone();
+        let harness = Harness::mount(
+            Widget::stateful(
+                (),
+                move |_, msg| {
+                    switch(msg).case(|&AnimationDone(id)| finished.borrow_mut().push(id));
+                    Propagate
+                },
+                |_| {
+                    let animation =
+                        Animation::new(0, 10, Duration::from_millis(100)).repeat(Repeat::Times(0));
+                    animate(animation, |x| text(x.to_string()))
+                },
+            ),
+            Size { x: 2, y: 1 },
+        );
+        harness.lines().must_be(vec!["10"]);
+        done.borrow().len().must_be(1);
+        crate::tick::next_due().must_be(None);
+    }
+
+    #[test]
+    fn rebuilds_of_the_parent_do_not_restart_animations() {
+        let mut harness = Harness::mount(
+            Widget::stateful(
+                0,
+                |this, msg| {
+                    switch(msg).case(|_: &Rebuild| this.set_state(|rebuilds| *rebuilds += 1));
+                    Propagate
+                },
+                |_| {
+                    animate(Animation::new(0, 10, Duration::from_millis(100)), |x| {
+                        text(x.to_string())
+                    })
+                },
+            ),
+            Size { x: 2, y: 1 },
+        );
+        harness.tick(FRAME);
+        harness.tick(Duration::from_millis(50));
+        harness.send(Rebuild);
+        harness.lines().must_be(vec!["5"]);
+        harness.tick(Duration::from_millis(50));
+        harness.lines().must_be(vec!["10"]);
+        harness.send(Rebuild);
+        harness.lines().must_be(vec!["10"]);
+        crate::tick::next_due().must_be(None);
+    }
+}
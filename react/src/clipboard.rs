@@ -0,0 +1,92 @@
+use std::cell::{Cell, RefCell};
+
+pub mod prelude {
+    pub use super::Paste;
+}
+
+/// Text pasted into the terminal, delivered like a key event to the focused widget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paste(pub String);
+
+thread_local! {
+    static REGISTER: RefCell<String> = const { RefCell::new(String::new()) };
+    static EXPORT: Cell<bool> = const { Cell::new(false) };
+    /// Copied text still to be handed to the host terminal
+    static PENDING: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Puts `s` into the clipboard register, and into the host terminal's clipboard when exporting
+pub fn copy(s: impl Into<String>) {
+    let s = s.into();
+    if EXPORT.get() {
+        PENDING.set(Some(s.clone()));
+    }
+    REGISTER.set(s);
+}
+
+/// What was copied last
+pub fn contents() -> String {
+    REGISTER.with_borrow(String::clone)
+}
+
+pub(crate) fn set_export(export: bool) {
+    EXPORT.set(export);
+}
+
+pub(crate) fn take_export() -> Option<String> {
+    PENDING.take()
+}
+
+/// The OSC 52 sequence asking the terminal to put `s` into the system clipboard
+pub(crate) fn osc52(s: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(s.as_bytes()))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | (byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn base64_pads_partial_chunks() {
+        base64(b"").must_be("");
+        base64(b"f").must_be("Zg==");
+        base64(b"fo").must_be("Zm8=");
+        base64(b"foo").must_be("Zm9v");
+        base64("héllo".as_bytes()).must_be("aMOpbGxv");
+    }
+
+    #[test]
+    fn copies_are_exported_only_when_enabled() {
+        copy("a");
+        take_export().must_be(None);
+        set_export(true);
+        copy("b");
+        contents().must_be("b");
+        take_export()
+            .map(|s| osc52(&s))
+            .must_be(Some("\x1b]52;c;Yg==\x07".to_string()));
+        take_export().must_be(None);
+        set_export(false);
+    }
+}
@@ -1,6 +1,16 @@
-use std::{cell::RefCell, fmt::Debug, rc::Rc};
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    fmt::Debug,
+    rc::Rc,
+    time::Duration,
+};
 
-use crate::{element::Element, prelude::Message};
+use crate::{
+    element::Element,
+    keymap::Keymap,
+    prelude::{Message, MessageFlow},
+};
 
 pub mod prelude {
     pub use super::{_Component, Component};
@@ -9,7 +19,34 @@ pub mod prelude {
 pub trait _Component: Debug {
     fn id(&self) -> usize;
     fn create_element(&mut self) -> (bool, Box<dyn Element>);
+    /// Delivers `event` to this widget and, unless it intercepts it, its children
     fn on_message(&mut self, event: &Message);
+    /// Delivers `msg` to the descendant at the end of `path`, a list of ids starting with a child
+    /// of this widget. When bubbling, each widget on the way back up handles it too until one
+    /// intercepts it.
+    fn deliver(&mut self, path: &[usize], msg: &Message, bubble: bool) -> MessageFlow;
+    fn set_focusable(&mut self, focusable: bool);
+    fn set_keymap(&mut self, keymap: Keymap);
+    /// Asks for a `Tick` every `interval`, or for none
+    fn set_tick_interval(&mut self, interval: Option<Duration>);
+    fn key(&self) -> Option<u64>;
+    fn set_key(&mut self, key: u64);
+    /// Identifies the function that made this component, so rebuilds only reuse like for like
+    fn kind(&self) -> TypeId;
+    /// The child components held in this component's state, for containers
+    fn children(&mut self) -> Vec<&mut Component>;
+    /// The components attached below this one: the built child and those in the state
+    fn attached(&mut self) -> Vec<Component>;
+    /// Called when this component is attached to the tree
+    fn on_mount(&mut self);
+    /// Called when a rebuild discards this component, or the app stops
+    fn on_unmount(&mut self);
+    fn add_mount_callback(&mut self, f: Box<dyn FnMut()>);
+    fn add_unmount_callback(&mut self, f: Box<dyn FnMut()>);
+    /// Takes over the builder, handlers and props of `new`, a freshly built component of the
+    /// same kind, keeping this instance with its id, state, hooks and tick interval
+    fn adopt(&mut self, new: &mut dyn _Component);
+    fn as_any(&mut self) -> &mut dyn Any;
 }
 
 pub type Component = Rc<RefCell<dyn _Component>>;
@@ -0,0 +1,163 @@
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    rc::Rc,
+};
+
+use crate::{component::prelude::*, hooks, message::MessageFlow::Propagate, widget::Widget};
+
+pub mod prelude {
+    pub use super::{provide, use_context};
+}
+
+/// A value made available to the descendants of a provider
+#[derive(Clone)]
+struct Provided {
+    type_id: TypeId,
+    value: Rc<dyn Any>,
+    eq: fn(&dyn Any, &dyn Any) -> bool,
+}
+
+thread_local! {
+    /// The values provided by the ancestors of the widget being built, innermost last
+    static PROVIDED: RefCell<Vec<Provided>> = const { RefCell::new(vec![]) };
+}
+
+fn eq<T: PartialEq + 'static>(a: &dyn Any, b: &dyn Any) -> bool {
+    a.downcast_ref::<T>() == b.downcast_ref::<T>()
+}
+
+fn lookup(type_id: TypeId) -> Option<Provided> {
+    PROVIDED.with_borrow(|provided| {
+        provided
+            .iter()
+            .rev()
+            .find(|provided| provided.type_id == type_id)
+            .cloned()
+    })
+}
+
+/// A context value a widget read while building, and what it was
+pub(crate) struct Dependency {
+    type_id: TypeId,
+    read: Option<Rc<dyn Any>>,
+}
+
+impl Dependency {
+    /// Whether the nearest provider now provides something else
+    pub(crate) fn is_stale(&self) -> bool {
+        match (lookup(self.type_id), &self.read) {
+            (None, None) => false,
+            (Some(now), Some(read)) => !(now.eq)(now.value.as_ref(), read.as_ref()),
+            _ => true,
+        }
+    }
+}
+
+/// Makes `value` available to every descendant of `child` through `use_context`.
+/// Descendants that read it rebuild when a rebuilt provider brings a different value.
+pub fn provide<T: PartialEq + 'static>(value: T, child: Component) -> Component {
+    Widget::container(
+        (
+            Provided {
+                type_id: TypeId::of::<T>(),
+                value: Rc::new(value),
+                eq: eq::<T>,
+            },
+            child,
+        ),
+        |(_, child)| vec![child],
+        |_, _| Propagate,
+        |this| {
+            let (provided, child) = &this.state;
+            PROVIDED.with_borrow_mut(|stack| stack.push(provided.clone()));
+            let built = child.borrow_mut().create_element();
+            PROVIDED.with_borrow_mut(|stack| stack.pop());
+            built
+        },
+    )
+}
+
+/// The value of type `T` provided by the nearest ancestor, for use in builders
+pub fn use_context<T: 'static>() -> Option<Rc<T>> {
+    let provided = lookup(TypeId::of::<T>());
+    hooks::depend(Dependency {
+        type_id: TypeId::of::<T>(),
+        read: provided.as_ref().map(|provided| provided.value.clone()),
+    });
+    provided.and_then(|provided| provided.value.downcast::<T>().ok())
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+
+    use crossterm::event::{KeyCode, KeyEvent};
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::{Harness, Size, column, text};
+
+    #[derive(Debug, PartialEq)]
+    struct Theme(&'static str);
+
+    /// Reads the theme a few widgets down, counting its builds
+    fn themed(builds: Rc<Cell<usize>>) -> Component {
+        Widget::function(move || {
+            builds.set(builds.get() + 1);
+            let theme = use_context::<Theme>();
+            text(theme.map_or("none", |theme| theme.0))
+        })
+    }
+
+    #[test]
+    fn descendants_read_the_nearest_provider() {
+        let harness = Harness::mount(
+            column([
+                provide(
+                    Theme("outer"),
+                    column([
+                        themed(Rc::default()),
+                        provide(Theme("inner"), themed(Rc::default())),
+                    ]),
+                ),
+                themed(Rc::default()),
+            ]),
+            Size { x: 5, y: 3 },
+        );
+        harness.lines().must_be(vec!["outer", "inner", "none"]);
+    }
+
+    #[test]
+    fn readers_rebuild_only_when_the_value_changes() {
+        let builds = Rc::new(Cell::new(0));
+        // Made once, so only a change of theme rebuilds it
+        let reader = themed(builds.clone());
+        let mut harness = Harness::mount(
+            Widget::stateful(
+                ("light", 0),
+                |this, msg| {
+                    switch(msg).case(|event: &KeyEvent| match event.code {
+                        KeyCode::Char('t') => this.set_state(|(theme, _)| *theme = "dark"),
+                        _ => this.set_state(|(_, presses)| *presses += 1),
+                    });
+                    Propagate
+                },
+                move |&(theme, presses)| {
+                    column([
+                        text(presses.to_string()),
+                        provide(Theme(theme), column([reader.clone()])),
+                    ])
+                },
+            ),
+            Size { x: 5, y: 2 },
+        );
+        harness.lines().must_be(vec!["0", "light"]);
+        harness.type_str("x");
+        harness.lines().must_be(vec!["1", "light"]);
+        builds.get().must_be(1);
+        harness.type_str("t");
+        harness.lines().must_be(vec!["1", "dark"]);
+        builds.get().must_be(2);
+    }
+}
@@ -1,9 +1,14 @@
 use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
 
-use crate::frame::{Frame, Token};
+use crate::{
+    frame::{Frame, Token},
+    style::{Color, Style},
+};
 
 pub mod prelude {
-    pub use super::{Direction, DisplayList, Operation, Point, Size, Vec2};
+    pub use super::{
+        Direction, DisplayList, Operation, Pen, Point, Rect, Region, Regions, Size, Vec2,
+    };
 }
 
 #[derive(Debug, Clone, Copy, Hash, Default, PartialEq)]
@@ -78,6 +83,41 @@ impl Neg for Vec2 {
 pub type Point = Vec2;
 pub type Size = Vec2;
 
+#[derive(Debug, Clone, Copy, Hash, Default, PartialEq)]
+pub struct Rect {
+    pub origin: Point,
+    pub size: Size,
+}
+
+impl Rect {
+    pub fn contains(&self, point: Point) -> bool {
+        (point - self.origin).within_constraint(&self.size)
+            && point.x >= self.origin.x
+            && point.y >= self.origin.y
+    }
+    /// The part of `self` that is also inside `other`, empty if they do not overlap
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let origin = Point {
+            x: self.origin.x.max(other.origin.x),
+            y: self.origin.y.max(other.origin.y),
+        };
+        let end = Point {
+            x: (self.origin.x + self.size.x).min(other.origin.x + other.size.x),
+            y: (self.origin.y + self.size.y).min(other.origin.y + other.size.y),
+        };
+        Rect {
+            origin,
+            size: Size {
+                x: (end.x - origin.x).max(0),
+                y: (end.y - origin.y).max(0),
+            },
+        }
+    }
+}
+
+/// Background of the cell under a text cursor
+pub const CURSOR_COLOR: Color = Color::Rgb(146, 146, 146);
+
 #[derive(Debug, Clone, Copy, Hash)]
 pub enum Direction {
     Start,
@@ -89,58 +129,171 @@ pub enum Direction {
 #[derive(Debug, Clone, Hash)]
 pub enum Operation {
     PutChar(char),
+    /// Puts a whole grapheme cluster, covering two cells if it is a wide glyph
+    PutGrapheme(String),
     DrawCursor,
     MoveTo(Point),
     Move(Direction),
     SetAnchor(Point),
+    /// Layers a style on top of the current one for every following `PutChar`
+    PushStyle(Style),
+    PopStyle,
+    /// Records that the widget with this id covers `Size` cells from the anchor
+    Region(usize, Size),
+    /// Discards everything drawn outside of `Size` cells from the anchor until the matching `PopClip`
+    PushClip(Size),
+    PopClip,
+}
+
+/// Drawing state carried between the operations of a `DisplayList`
+#[derive(Debug, Clone, Default)]
+pub struct Pen {
+    pub anchor: Point,
+    pub offset: Point,
+    pub styles: Vec<Style>,
+    pub clips: Vec<Rect>,
+    pub regions: Regions,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub id: usize,
+    /// Where the widget was laid out, which can extend past the screen when it is scrolled
+    pub rect: Rect,
+    /// The part of `rect` that is actually visible
+    pub visible: Rect,
+}
+
+/// Where each widget was drawn, in drawing order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Regions(pub Vec<Region>);
+
+impl Regions {
+    pub fn get(&self, id: usize) -> Option<Rect> {
+        self.0
+            .iter()
+            .find_map(|region| (region.id == id).then_some(region.rect))
+    }
+    /// The innermost widget visible at `point`
+    pub fn hit(&self, point: Point) -> Option<usize> {
+        self.0
+            .iter()
+            .rev()
+            .find_map(|region| region.visible.contains(point).then_some(region.id))
+    }
+}
+
+impl Pen {
+    pub fn style(&self) -> Style {
+        self.styles.last().copied().unwrap_or_default()
+    }
+
+    fn is_clipped(&self, point: Point) -> bool {
+        self.clips.last().is_some_and(|clip| !clip.contains(point))
+    }
+
+    fn cell<'a>(&self, buffer: &'a mut Frame) -> Option<&'a mut Token> {
+        let target = self.anchor + self.offset;
+        if target.x < 0 || target.y < 0 || self.is_clipped(target) {
+            return None;
+        }
+        buffer
+            .get_mut(target.y as usize)?
+            .get_mut(target.x as usize)
+    }
+
+    fn put(&self, token: Token, buffer: &mut Frame) {
+        let target = self.anchor + self.offset;
+        if target.x < 0 || target.y < 0 || self.is_clipped(target) {
+            return;
+        }
+        let Some(row) = buffer.get_mut(target.y as usize) else {
+            return;
+        };
+        let x = target.x as usize;
+        if x >= row.len() {
+            return;
+        }
+        let style = token.style().unwrap_or_default();
+        let (token, cells) = match token.width() {
+            2 if x + 1 < row.len() && !self.is_clipped(target + Point { x: 1, y: 0 }) => (token, 2),
+            2 => (Token::Char(' ', style), 1),
+            _ => (token, 1),
+        };
+        // Never leave half of a wide glyph behind
+        if row[x] == Token::Continuation
+            && let Some(lead) = x.checked_sub(1).and_then(|lead| row.get_mut(lead))
+        {
+            *lead = Token::Char(' ', lead.style().unwrap_or_default());
+        }
+        let end = x + cells;
+        if row.get(end) == Some(&Token::Continuation) {
+            row[end] = Token::Char(' ', row[end - 1].style().unwrap_or_default());
+        }
+        row[x] = token;
+        if cells == 2 {
+            row[x + 1] = Token::Continuation;
+        }
+    }
 }
 
 impl Operation {
-    pub fn realize(self, anchor: &mut Point, offset: &mut Point, buffer: &mut Frame) {
+    pub fn realize(self, pen: &mut Pen, buffer: &mut Frame) {
         match self {
             Operation::PutChar(c) => {
-                let target = *anchor + *offset;
-                if target.y >= 0
-                    && let Some(row) = buffer.get_mut(target.y as usize)
-                {
-                    if target.x >= 0
-                        && let Some(col) = row.get_mut(target.x as usize)
-                    {
-                        *col = match col {
-                            Token::Char(_) => Token::Char(c),
-                            Token::AnnotatedChar(s1, _, s2) => Token::AnnotatedChar(s1, c, s2),
-                        };
-                    }
-                }
+                pen.put(Token::Char(c, pen.style()), buffer);
+            }
+            Operation::PutGrapheme(grapheme) => {
+                pen.put(Token::grapheme(&grapheme, pen.style()), buffer);
             }
             Operation::MoveTo(point) => {
-                *offset = point;
+                pen.offset = point;
             }
             Operation::Move(direction) => {
-                if let Some(new_offset) = offset.adjacent(direction) {
-                    *offset = new_offset;
+                if let Some(new_offset) = pen.offset.adjacent(direction) {
+                    pen.offset = new_offset;
                 }
             }
             Operation::SetAnchor(point) => {
-                *anchor += point;
-                *offset = Point::default();
+                pen.anchor += point;
+                pen.offset = Point::default();
             }
             Operation::DrawCursor => {
-                let target = *anchor + *offset;
-                if target.y >= 0
-                    && let Some(row) = buffer.get_mut(target.y as usize)
-                {
-                    if target.x >= 0
-                        && let Some(col) = row.get_mut(target.x as usize)
-                    {
-                        *col = match col {
-                            Token::AnnotatedChar(_, c, _) | Token::Char(c) => {
-                                Token::AnnotatedChar("\x1b[48;2;146;146;146m", *c, "\x1b[0m")
-                            }
-                        };
-                    }
+                if let Some(style) = pen.cell(buffer).and_then(Token::style_mut) {
+                    *style = style.bg(CURSOR_COLOR);
                 }
             }
+            Operation::PushStyle(style) => {
+                pen.styles.push(pen.style().patch(style));
+            }
+            Operation::PopStyle => {
+                pen.styles.pop();
+            }
+            Operation::Region(id, size) => {
+                let rect = Rect {
+                    origin: pen.anchor,
+                    size,
+                };
+                let visible = match pen.clips.last() {
+                    Some(clip) => rect.intersection(clip),
+                    None => rect,
+                };
+                pen.regions.0.push(Region { id, rect, visible });
+            }
+            Operation::PushClip(size) => {
+                let clip = Rect {
+                    origin: pen.anchor,
+                    size,
+                };
+                let clip = match pen.clips.last() {
+                    Some(outer) => clip.intersection(outer),
+                    None => clip,
+                };
+                pen.clips.push(clip);
+            }
+            Operation::PopClip => {
+                pen.clips.pop();
+            }
         }
     }
 }
@@ -155,61 +308,83 @@ impl<T: Into<Vec<Operation>>> From<T> for DisplayList {
 }
 
 impl DisplayList {
-    pub fn draw_on(self, buffer: &mut Frame) {
-        let mut anchor = Point::default();
-        let mut offset = Point::default();
+    /// Draws onto `buffer`, returning where each widget ended up
+    pub fn draw_on(self, buffer: &mut Frame) -> Regions {
+        let mut pen = Pen::default();
         self.0
             .into_iter()
-            .for_each(|op| op.realize(&mut anchor, &mut offset, buffer));
-    }
-}
-
-// #[cfg(test)]
-// mod test {
-//     use super::*;
-//     use stdext::prelude::*;
-
-//     #[test]
-//     fn test6() {
-//         let mut buffer = vec![vec![' '; 5]; 5];
-//         DisplayList::default().draw_on(&mut buffer);
-//         buffer.must_be(vec![
-//             vec![' ', ' ', ' ', ' ', ' '],
-//             vec![' ', ' ', ' ', ' ', ' '],
-//             vec![' ', ' ', ' ', ' ', ' '],
-//             vec![' ', ' ', ' ', ' ', ' '],
-//             vec![' ', ' ', ' ', ' ', ' '],
-//         ]);
-//     }
-//     #[test]
-//     fn test7() {
-//         let mut buffer = vec![vec![' '; 5]; 5];
-//         DisplayList::from([Operation::PutChar('a')]).draw_on(&mut buffer);
-//         buffer.must_be(vec![
-//             vec![' ', ' ', ' ', ' ', ' '],
-//             vec![' ', ' ', 'a', ' ', ' '],
-//             vec![' ', ' ', ' ', ' ', ' '],
-//             vec![' ', ' ', ' ', ' ', ' '],
-//             vec![' ', ' ', ' ', ' ', ' '],
-//         ]);
-//     }
-//     #[test]
-//     fn test8() {
-//         let mut buffer = vec![vec![' '; 5]; 5];
-//         DisplayList(vec![
-//             Operation::PutChar('a'),
-//             Operation::Move(Direction::End),
-//             Operation::PutChar('b'),
-//             Operation::Move(Direction::End),
-//             Operation::PutChar('c'),
-//         ])
-//         .draw_on(&mut buffer);
-//         buffer.must_be(vec![
-//             vec![' ', ' ', ' ', ' ', ' '],
-//             vec![' ', ' ', 'a', 'b', 'c'],
-//             vec![' ', ' ', ' ', ' ', ' '],
-//             vec![' ', ' ', ' ', ' ', ' '],
-//             vec![' ', ' ', ' ', ' ', ' '],
-//         ]);
-//     }
-// }
+            .for_each(|op| op.realize(&mut pen, buffer));
+        pen.regions
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::frame::TokensExt;
+    use stdext::prelude::*;
+
+    fn drawn(display_list: DisplayList) -> Vec<String> {
+        let mut buffer = vec![vec![Token::default(); 5]; 3];
+        _ = display_list.draw_on(&mut buffer);
+        buffer.iter().map(|row| row.to_string()).collect()
+    }
+
+    #[test]
+    fn test6() {
+        drawn(DisplayList::default()).must_be(vec!["     ", "     ", "     "]);
+    }
+    #[test]
+    fn test7() {
+        drawn(DisplayList::from([
+            Operation::SetAnchor(Point { x: 2, y: 1 }),
+            Operation::PutChar('a'),
+        ]))
+        .must_be(vec!["     ", "  a  ", "     "]);
+    }
+    #[test]
+    fn test8() {
+        drawn(DisplayList(vec![
+            Operation::SetAnchor(Point { x: 2, y: 1 }),
+            Operation::PutChar('a'),
+            Operation::Move(Direction::End),
+            Operation::PutChar('b'),
+            Operation::Move(Direction::End),
+            Operation::PutChar('c'),
+        ]))
+        .must_be(vec!["     ", "  abc", "     "]);
+    }
+    #[test]
+    fn wide_glyphs_do_not_leave_halves_behind() {
+        drawn(DisplayList(vec![
+            Operation::PutChar('中'),
+            Operation::Move(Direction::End),
+            Operation::PutChar('x'),
+            Operation::MoveTo(Point { x: 4, y: 0 }),
+            Operation::PutChar('中'),
+        ]))
+        .must_be(vec![" x   ", "     ", "     "]);
+    }
+
+    #[test]
+    fn clips_nest_and_cut_wide_glyphs() {
+        drawn(DisplayList(vec![
+            Operation::SetAnchor(Point { x: 1, y: 0 }),
+            Operation::PushClip(Size { x: 3, y: 2 }),
+            Operation::PushClip(Size { x: 9, y: 1 }),
+            Operation::PutChar('a'),
+            Operation::MoveTo(Point { x: 0, y: 1 }),
+            Operation::PutChar('b'),
+            Operation::PopClip,
+            Operation::PutChar('c'),
+            Operation::MoveTo(Point { x: 2, y: 0 }),
+            Operation::PutChar('中'),
+            Operation::MoveTo(Point { x: 3, y: 1 }),
+            Operation::PutChar('d'),
+            Operation::PopClip,
+            Operation::MoveTo(Point { x: 0, y: 2 }),
+            Operation::PutChar('e'),
+        ]))
+        .must_be(vec![" a   ", " c   ", " e   "]);
+    }
+}
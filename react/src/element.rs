@@ -6,4 +6,9 @@ pub mod prelude {
 
 pub trait Element: Send {
     fn draw(&self, constraint: Size, display_list: &mut DisplayList);
+    /// The size this element would like to take up when given at most `constraint`.
+    /// Elements that fill whatever they are given can keep the default.
+    fn size(&self, constraint: Size) -> Size {
+        constraint
+    }
 }
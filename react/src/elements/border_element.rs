@@ -0,0 +1,100 @@
+use crate::{
+    prelude::{DisplayList, Element, Insets, Operation, Point, Size, Style},
+    unicode::{graphemes, truncate, width},
+};
+
+pub mod prelude {
+    pub use super::{BorderElement, Lines};
+}
+
+/// The box-drawing characters a border is made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Lines {
+    #[default]
+    Single,
+    Double,
+    Rounded,
+    Heavy,
+}
+
+impl Lines {
+    /// Top left, top right, bottom left and bottom right corners, then horizontal and vertical edges
+    fn chars(self) -> [char; 6] {
+        match self {
+            Lines::Single => ['┌', '┐', '└', '┘', '─', '│'],
+            Lines::Double => ['╔', '╗', '╚', '╝', '═', '║'],
+            Lines::Rounded => ['╭', '╮', '╰', '╯', '─', '│'],
+            Lines::Heavy => ['┏', '┓', '┗', '┛', '━', '┃'],
+        }
+    }
+}
+
+/// Draws a border around `child`, with `title` set into the top edge
+pub struct BorderElement {
+    pub lines: Lines,
+    pub title: Option<String>,
+    pub style: Style,
+    pub child: Box<dyn Element>,
+}
+
+impl Element for BorderElement {
+    fn draw(&self, constraint: Size, display_list: &mut DisplayList) {
+        let insets = Insets::all(1);
+        if constraint.x >= 2 && constraint.y >= 2 {
+            let [
+                top_left,
+                top_right,
+                bottom_left,
+                bottom_right,
+                horizontal,
+                vertical,
+            ] = self.lines.chars();
+            let (right, bottom) = (constraint.x - 1, constraint.y - 1);
+            display_list.0.push(Operation::PushStyle(self.style));
+            let mut put = |x, y, c| {
+                display_list.0.push(Operation::MoveTo(Point { x, y }));
+                display_list.0.push(Operation::PutChar(c));
+            };
+            put(0, 0, top_left);
+            put(right, 0, top_right);
+            put(0, bottom, bottom_left);
+            put(right, bottom, bottom_right);
+            for x in 1..right {
+                put(x, 0, horizontal);
+                put(x, bottom, horizontal);
+            }
+            for y in 1..bottom {
+                put(0, y, vertical);
+                put(right, y, vertical);
+            }
+            // Keep a space and an edge cell on either side of the title
+            if let Some(title) = &self.title
+                && constraint.x - 4 >= 1
+            {
+                let title = format!(" {} ", truncate(title, (constraint.x - 4) as usize));
+                let mut x = 1;
+                for g in graphemes(&title) {
+                    display_list.0.push(Operation::MoveTo(Point { x, y: 0 }));
+                    display_list.0.push(Operation::PutGrapheme(g.to_string()));
+                    x += width(g) as isize;
+                }
+            }
+            display_list.0.push(Operation::PopStyle);
+        }
+        display_list.0.push(Operation::SetAnchor(insets.origin()));
+        self.child.draw(insets.shrink(constraint), display_list);
+        display_list.0.push(Operation::SetAnchor(-insets.origin()));
+    }
+    fn size(&self, constraint: Size) -> Size {
+        let insets = Insets::all(1);
+        let size = self.child.size(insets.shrink(constraint)) + insets.size();
+        let title = self
+            .title
+            .as_ref()
+            .map_or(0, |title| width(title) as isize + 4);
+        Size {
+            x: size.x.max(title).min(constraint.x),
+            y: size.y.min(constraint.y),
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use unicode_width::UnicodeWidthChar;
+
 use crate::prelude::{DisplayList, Element, Frame, Operation, Size};
 
 pub mod prelude {
@@ -12,4 +14,10 @@ impl Element for CharElement {
     fn draw(&self, _constraint: Size, display_list: &mut DisplayList) {
         display_list.0.push(Operation::PutChar(self.c));
     }
+    fn size(&self, constraint: Size) -> Size {
+        Size {
+            x: (self.c.width().unwrap_or(1) as isize).min(constraint.x),
+            y: 1.min(constraint.y),
+        }
+    }
 }
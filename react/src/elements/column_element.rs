@@ -1,46 +1,28 @@
-use crate::prelude::{DisplayList, Element, Frame, Operation, Point, Size};
+use crate::{
+    elements::flex::{self, Axis, FlexItem, FlexLayout},
+    prelude::{DisplayList, Element, Size},
+};
 
 pub mod prelude {
     pub use super::ColumnElement;
 }
 
 pub struct ColumnElement {
-    pub children: Vec<Box<dyn Element>>,
+    pub layout: FlexLayout,
+    pub children: Vec<FlexItem>,
 }
 
 impl Element for ColumnElement {
     fn draw(&self, constraint: Size, display_list: &mut DisplayList) {
-        let child_height = constraint.y as usize / self.children.len();
-        let mut y_offset = 0;
-        for child in &self.children {
-            let offset = Point {
-                x: 0,
-                y: y_offset as isize,
-            };
-            display_list.0.push(Operation::SetAnchor(offset));
-            child.draw(
-                Size {
-                    x: constraint.x,
-                    y: child_height as isize,
-                },
-                display_list,
-            );
-            display_list.0.push(Operation::SetAnchor(-offset));
-            y_offset += child_height;
-        }
+        flex::draw(
+            Axis::Vertical,
+            &self.layout,
+            &self.children,
+            constraint,
+            display_list,
+        );
+    }
+    fn size(&self, constraint: Size) -> Size {
+        flex::size(Axis::Vertical, &self.layout, &self.children, constraint)
     }
-    // fn draw(&self) -> Frame {
-    //     self.children
-    //         .iter()
-    //         .map(|child| {
-    //             let mut frame = child.draw();
-    //             frame.align_width();
-    //             frame
-    //         })
-    //         .reduce(|mut acc, mut frame| {
-    //             acc.append(&mut frame);
-    //             acc
-    //         })
-    //         .unwrap_or_else(|| vec![vec![]])
-    // }
 }
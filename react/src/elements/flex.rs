@@ -0,0 +1,337 @@
+use crate::prelude::{DisplayList, Element, Operation, Point, Size};
+
+pub mod prelude {
+    pub use super::{Align, Axis, Flex, FlexItem, FlexLayout, Justify, Sizing};
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Axis {
+    fn main(self, size: Size) -> isize {
+        match self {
+            Axis::Horizontal => size.x,
+            Axis::Vertical => size.y,
+        }
+    }
+    fn cross(self, size: Size) -> isize {
+        match self {
+            Axis::Horizontal => size.y,
+            Axis::Vertical => size.x,
+        }
+    }
+    fn size(self, main: isize, cross: isize) -> Size {
+        match self {
+            Axis::Horizontal => Size { x: main, y: cross },
+            Axis::Vertical => Size { x: cross, y: main },
+        }
+    }
+}
+
+/// How much of the main axis a child gets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sizing {
+    /// Exactly this many cells
+    Fixed(isize),
+    /// The child's intrinsic size
+    Fit,
+    /// A share of the space left over by the other children, proportional to the factor
+    Grow(usize),
+}
+
+/// Layout parameters of a single child of a row or column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Flex {
+    pub sizing: Sizing,
+    pub min: isize,
+    pub max: Option<isize>,
+}
+
+impl Default for Flex {
+    fn default() -> Self {
+        Flex::grow(1)
+    }
+}
+
+impl Flex {
+    pub fn fixed(size: isize) -> Self {
+        Self {
+            sizing: Sizing::Fixed(size),
+            min: 0,
+            max: None,
+        }
+    }
+    pub fn fit() -> Self {
+        Self {
+            sizing: Sizing::Fit,
+            min: 0,
+            max: None,
+        }
+    }
+    pub fn grow(factor: usize) -> Self {
+        Self {
+            sizing: Sizing::Grow(factor),
+            min: 0,
+            max: None,
+        }
+    }
+    pub fn min(self, min: isize) -> Self {
+        Self { min, ..self }
+    }
+    pub fn max(self, max: isize) -> Self {
+        Self {
+            max: Some(max),
+            ..self
+        }
+    }
+    fn clamp(&self, size: isize) -> isize {
+        let size = size.max(self.min);
+        match self.max {
+            Some(max) => size.min(max.max(self.min)),
+            None => size,
+        }
+    }
+}
+
+/// Distribution of free space along the main axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Justify {
+    #[default]
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+}
+
+/// Placement of children along the cross axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    #[default]
+    Stretch,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FlexLayout {
+    pub gap: isize,
+    pub justify: Justify,
+    pub align: Align,
+}
+
+pub struct FlexItem {
+    pub flex: Flex,
+    pub element: Box<dyn Element>,
+}
+
+fn gaps(layout: &FlexLayout, children: &[FlexItem]) -> isize {
+    layout.gap * (children.len() as isize - 1).max(0)
+}
+
+/// Main axis sizes of `children` sharing `constraint`
+fn main_sizes(
+    axis: Axis,
+    layout: &FlexLayout,
+    children: &[FlexItem],
+    constraint: Size,
+) -> Vec<isize> {
+    let mut sizes = children
+        .iter()
+        .map(|child| match child.flex.sizing {
+            Sizing::Fixed(size) => Some(child.flex.clamp(size)),
+            Sizing::Fit => Some(child.flex.clamp(axis.main(child.element.size(constraint)))),
+            Sizing::Grow(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let mut free =
+        axis.main(constraint) - gaps(layout, children) - sizes.iter().flatten().sum::<isize>();
+    // Growing children that hit their min or max are frozen at it and the rest is shared again
+    loop {
+        let growing = children
+            .iter()
+            .zip(&sizes)
+            .filter_map(|(child, size)| match (child.flex.sizing, size) {
+                (Sizing::Grow(factor), None) => Some(factor),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let total = growing.iter().sum::<usize>();
+        if growing.is_empty() {
+            break;
+        }
+        let available = free.max(0);
+        let mut remainder = available;
+        let mut shares = growing
+            .iter()
+            .map(|&factor| {
+                let share = if total == 0 {
+                    0
+                } else {
+                    available * factor as isize / total as isize
+                };
+                remainder -= share;
+                share
+            })
+            .collect::<Vec<_>>();
+        // Hand out the cells lost to rounding one by one instead of dropping them
+        for share in shares.iter_mut() {
+            if remainder <= 0 {
+                break;
+            }
+            *share += 1;
+            remainder -= 1;
+        }
+        let mut shares = shares.into_iter();
+        let mut frozen = false;
+        let assigned = children
+            .iter()
+            .zip(&sizes)
+            .map(|(child, size)| match size {
+                Some(size) => (*size, true),
+                None => {
+                    let share = shares.next().unwrap_or(0);
+                    let clamped = child.flex.clamp(share);
+                    frozen |= clamped != share;
+                    (clamped, clamped != share)
+                }
+            })
+            .collect::<Vec<_>>();
+        if !frozen {
+            return assigned.into_iter().map(|(size, _)| size).collect();
+        }
+        for (size, (assigned, is_frozen)) in sizes.iter_mut().zip(assigned) {
+            if size.is_none() && is_frozen {
+                *size = Some(assigned);
+                free -= assigned;
+            }
+        }
+    }
+    sizes.into_iter().map(|size| size.unwrap_or(0)).collect()
+}
+
+pub(crate) fn size(
+    axis: Axis,
+    layout: &FlexLayout,
+    children: &[FlexItem],
+    constraint: Size,
+) -> Size {
+    let main = children
+        .iter()
+        .map(|child| match child.flex.sizing {
+            Sizing::Fixed(size) => child.flex.clamp(size),
+            Sizing::Fit | Sizing::Grow(_) => {
+                child.flex.clamp(axis.main(child.element.size(constraint)))
+            }
+        })
+        .sum::<isize>()
+        + gaps(layout, children);
+    let cross = children
+        .iter()
+        .map(|child| axis.cross(child.element.size(constraint)))
+        .max()
+        .unwrap_or(0);
+    axis.size(
+        main.min(axis.main(constraint)),
+        cross.min(axis.cross(constraint)),
+    )
+}
+
+pub(crate) fn draw(
+    axis: Axis,
+    layout: &FlexLayout,
+    children: &[FlexItem],
+    constraint: Size,
+    display_list: &mut DisplayList,
+) {
+    let sizes = main_sizes(axis, layout, children, constraint);
+    let free =
+        (axis.main(constraint) - gaps(layout, children) - sizes.iter().sum::<isize>()).max(0);
+    let (mut position, spacing) = match layout.justify {
+        Justify::Start => (0, 0),
+        Justify::Center => (free / 2, 0),
+        Justify::End => (free, 0),
+        Justify::SpaceBetween if children.len() > 1 => (0, free / (children.len() as isize - 1)),
+        Justify::SpaceBetween => (0, 0),
+    };
+    let cross_constraint = axis.cross(constraint);
+    for (child, main) in children.iter().zip(sizes) {
+        let cross = match layout.align {
+            Align::Stretch => cross_constraint,
+            _ => axis
+                .cross(child.element.size(axis.size(main, cross_constraint)))
+                .min(cross_constraint),
+        };
+        let cross_position = match layout.align {
+            Align::Start | Align::Stretch => 0,
+            Align::Center => (cross_constraint - cross) / 2,
+            Align::End => cross_constraint - cross,
+        };
+        let offset: Point = axis.size(position, cross_position);
+        display_list.0.push(Operation::SetAnchor(offset));
+        child.element.draw(axis.size(main, cross), display_list);
+        display_list.0.push(Operation::SetAnchor(-offset));
+        position += main + layout.gap + spacing;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::StringElement;
+
+    fn item(flex: Flex, s: &str) -> FlexItem {
+        FlexItem {
+            flex,
+            element: Box::new(StringElement {
+                s: s.to_string(),
+                cursor: None,
+                selection: None,
+                style: Default::default(),
+            }),
+        }
+    }
+
+    fn sizes(layout: FlexLayout, children: &[FlexItem], length: isize) -> Vec<isize> {
+        main_sizes(Axis::Vertical, &layout, children, Size { x: 10, y: length })
+    }
+
+    #[test]
+    fn remainder_is_not_dropped() {
+        let children = [
+            item(Flex::grow(1), ""),
+            item(Flex::grow(1), ""),
+            item(Flex::grow(1), ""),
+        ];
+        sizes(FlexLayout::default(), &children, 10).must_be(vec![4, 3, 3]);
+    }
+
+    #[test]
+    fn fit_and_fixed_children_leave_the_rest_to_growing_ones() {
+        let children = [
+            item(Flex::fit(), "a\nb"),
+            item(Flex::grow(1), ""),
+            item(Flex::fixed(1), ""),
+        ];
+        sizes(FlexLayout::default(), &children, 10).must_be(vec![2, 7, 1]);
+    }
+
+    #[test]
+    fn factors_gaps_and_bounds() {
+        let children = [item(Flex::grow(1), ""), item(Flex::grow(2), "")];
+        let layout = FlexLayout {
+            gap: 1,
+            ..Default::default()
+        };
+        sizes(layout, &children, 10).must_be(vec![3, 6]);
+        let children = [item(Flex::grow(1).max(2), ""), item(Flex::grow(1), "")];
+        sizes(layout, &children, 10).must_be(vec![2, 7]);
+        let children = [item(Flex::grow(1).min(8), ""), item(Flex::grow(1), "")];
+        sizes(layout, &children, 10).must_be(vec![8, 1]);
+    }
+}
@@ -1,11 +1,19 @@
+pub mod border_element;
 pub mod char_element;
 pub mod column_element;
+pub mod flex;
+pub mod padding_element;
+pub mod region_element;
 pub mod row_element;
+pub mod scroll_element;
 pub mod string_element;
+pub mod table_element;
 
 pub mod prelude {
     pub use super::{
-        char_element::prelude::*, column_element::prelude::*, row_element::prelude::*,
-        string_element::prelude::*,
+        border_element::prelude::*, char_element::prelude::*, column_element::prelude::*,
+        flex::prelude::*, padding_element::prelude::*, region_element::prelude::*,
+        row_element::prelude::*, scroll_element::prelude::*, string_element::prelude::*,
+        table_element::prelude::*,
     };
 }
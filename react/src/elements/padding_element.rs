@@ -0,0 +1,77 @@
+use crate::prelude::{DisplayList, Element, Operation, Point, Size};
+
+pub mod prelude {
+    pub use super::{Insets, PaddingElement};
+}
+
+/// Space kept free on each side of something
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Insets {
+    pub top: isize,
+    pub right: isize,
+    pub bottom: isize,
+    pub left: isize,
+}
+
+impl Insets {
+    pub fn all(inset: isize) -> Self {
+        Self {
+            top: inset,
+            right: inset,
+            bottom: inset,
+            left: inset,
+        }
+    }
+    pub fn symmetric(vertical: isize, horizontal: isize) -> Self {
+        Self {
+            top: vertical,
+            right: horizontal,
+            bottom: vertical,
+            left: horizontal,
+        }
+    }
+    pub fn origin(&self) -> Point {
+        Point {
+            x: self.left,
+            y: self.top,
+        }
+    }
+    /// Total space taken up along each axis
+    pub fn size(&self) -> Size {
+        Size {
+            x: self.left + self.right,
+            y: self.top + self.bottom,
+        }
+    }
+    /// What is left of `size` inside the insets
+    pub fn shrink(&self, size: Size) -> Size {
+        let inner = size - self.size();
+        Size {
+            x: inner.x.max(0),
+            y: inner.y.max(0),
+        }
+    }
+}
+
+/// Draws `child` inset from the edges of its constraint
+pub struct PaddingElement {
+    pub insets: Insets,
+    pub child: Box<dyn Element>,
+}
+
+impl Element for PaddingElement {
+    fn draw(&self, constraint: Size, display_list: &mut DisplayList) {
+        let origin = self.insets.origin();
+        display_list.0.push(Operation::SetAnchor(origin));
+        self.child
+            .draw(self.insets.shrink(constraint), display_list);
+        display_list.0.push(Operation::SetAnchor(-origin));
+    }
+    fn size(&self, constraint: Size) -> Size {
+        let size = self.child.size(self.insets.shrink(constraint)) + self.insets.size();
+        Size {
+            x: size.x.min(constraint.x),
+            y: size.y.min(constraint.y),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use crate::prelude::{DisplayList, Element, Operation, Size};
+
+pub mod prelude {
+    pub use super::RegionElement;
+}
+
+/// Marks the area `child` is drawn in as belonging to the widget `id`, for hit-testing
+pub struct RegionElement {
+    pub id: usize,
+    pub child: Box<dyn Element>,
+}
+
+impl Element for RegionElement {
+    fn draw(&self, constraint: Size, display_list: &mut DisplayList) {
+        display_list.0.push(Operation::Region(self.id, constraint));
+        self.child.draw(constraint, display_list);
+    }
+    fn size(&self, constraint: Size) -> Size {
+        self.child.size(constraint)
+    }
+}
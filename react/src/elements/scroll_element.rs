@@ -0,0 +1,177 @@
+use std::sync::{Arc, Mutex};
+
+use crate::prelude::{DisplayList, Element, Operation, Point, Size, Style};
+
+pub mod prelude {
+    pub use super::{ScrollElement, ScrollMetrics};
+}
+
+/// Stands in for an unlimited length when measuring content
+const UNBOUNDED: isize = i32::MAX as isize;
+
+/// What a `ScrollElement` found when it was last drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScrollMetrics {
+    /// The offset actually used, after clamping to the content
+    pub offset: Point,
+    pub viewport: Size,
+    pub content: Size,
+}
+
+impl ScrollMetrics {
+    /// Clamps `offset` so that the viewport stays within the content
+    pub fn clamp(&self, offset: Point) -> Point {
+        Point {
+            x: offset.x.min(self.content.x - self.viewport.x).max(0),
+            y: offset.y.min(self.content.y - self.viewport.y).max(0),
+        }
+    }
+}
+
+/// Lays `child` out at its full height, and at its full width when scrolling `horizontal`ly,
+/// then shows the part of it starting at `offset` with scrollbars where it overflows
+pub struct ScrollElement {
+    pub child: Box<dyn Element>,
+    pub offset: Point,
+    pub horizontal: bool,
+    pub metrics: Arc<Mutex<ScrollMetrics>>,
+}
+
+impl ScrollElement {
+    fn content(&self, viewport: Size) -> Size {
+        let width = if self.horizontal {
+            UNBOUNDED
+        } else {
+            viewport.x
+        };
+        let content = self.child.size(Size {
+            x: width,
+            y: UNBOUNDED,
+        });
+        Size {
+            x: if self.horizontal {
+                content.x
+            } else {
+                viewport.x
+            },
+            y: content.y,
+        }
+    }
+
+    /// The viewport left over by the scrollbars and the size of the content shown in it
+    fn measure(&self, constraint: Size) -> (Size, Size) {
+        // Scrollbars only ever shrink the viewport, which can only make more of them necessary
+        let mut viewport = constraint;
+        loop {
+            let content = self.content(viewport);
+            let bars = Size {
+                x: (content.y > viewport.y) as isize,
+                y: (self.horizontal && content.x > viewport.x) as isize,
+            };
+            let next = constraint - bars;
+            if next == viewport {
+                return (viewport, content);
+            }
+            viewport = next;
+        }
+    }
+}
+
+/// Start and length of a scrollbar thumb along a track of `viewport` cells
+fn thumb(offset: isize, viewport: isize, content: isize) -> (isize, isize) {
+    let length = (viewport * viewport / content.max(1)).clamp(1, viewport.max(1));
+    let scrollable = content - viewport;
+    let start = if scrollable > 0 {
+        offset * (viewport - length) / scrollable
+    } else {
+        0
+    };
+    (start, length)
+}
+
+fn draw_bar(
+    display_list: &mut DisplayList,
+    (start, length): (isize, isize),
+    cells: impl Iterator<Item = (isize, Point)>,
+    track: char,
+) {
+    for (i, point) in cells {
+        display_list.0.push(Operation::MoveTo(point));
+        if (start..start + length).contains(&i) {
+            display_list.0.push(Operation::PutChar('█'));
+        } else {
+            display_list
+                .0
+                .push(Operation::PushStyle(Style::default().dim()));
+            display_list.0.push(Operation::PutChar(track));
+            display_list.0.push(Operation::PopStyle);
+        }
+    }
+}
+
+impl Element for ScrollElement {
+    fn draw(&self, constraint: Size, display_list: &mut DisplayList) {
+        let (viewport, content) = self.measure(constraint);
+        let metrics = ScrollMetrics {
+            offset: Point::default(),
+            viewport,
+            content,
+        };
+        let offset = metrics.clamp(self.offset);
+        *self.metrics.lock().expect("Scroll metrics poisoned") =
+            ScrollMetrics { offset, ..metrics };
+
+        display_list.0.push(Operation::PushClip(viewport));
+        display_list.0.push(Operation::SetAnchor(-offset));
+        self.child.draw(
+            Size {
+                x: content.x.max(viewport.x),
+                y: content.y.max(viewport.y),
+            },
+            display_list,
+        );
+        display_list.0.push(Operation::SetAnchor(offset));
+        display_list.0.push(Operation::PopClip);
+
+        if viewport.x < constraint.x {
+            draw_bar(
+                display_list,
+                thumb(offset.y, viewport.y, content.y),
+                (0..viewport.y).map(|y| (y, Point { x: viewport.x, y })),
+                '│',
+            );
+        }
+        if viewport.y < constraint.y {
+            draw_bar(
+                display_list,
+                thumb(offset.x, viewport.x, content.x),
+                (0..viewport.x).map(|x| (x, Point { x, y: viewport.y })),
+                '─',
+            );
+        }
+    }
+    fn size(&self, constraint: Size) -> Size {
+        let (viewport, content) = self.measure(constraint);
+        Size {
+            x: content.x.min(viewport.x) + constraint.x - viewport.x,
+            y: content.y.min(viewport.y) + constraint.y - viewport.y,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn thumbs_span_the_track_proportionally() {
+        thumb(0, 4, 8).must_be((0, 2));
+        thumb(4, 4, 8).must_be((2, 2));
+        thumb(2, 4, 8).must_be((1, 2));
+        thumb(0, 4, 400).must_be((0, 1));
+        thumb(396, 4, 400).must_be((3, 1));
+        thumb(0, 4, 4).must_be((0, 4));
+    }
+}
@@ -1,4 +1,9 @@
-use crate::prelude::{DisplayList, Element, Operation, Point, Size};
+use std::ops::Range;
+
+use crate::{
+    prelude::{DisplayList, Element, Operation, Point, Size, Style},
+    unicode::{graphemes, is_newline, width},
+};
 
 pub mod prelude {
     pub use super::StringElement;
@@ -6,46 +11,240 @@ pub mod prelude {
 
 pub struct StringElement {
     pub s: String,
+    /// Index of the grapheme the cursor is on; the grapheme count puts it after the last one
     pub cursor: Option<usize>,
+    /// Graphemes shown highlighted, such as selected text
+    pub selection: Option<Range<usize>>,
+    pub style: Style,
+}
+
+/// Where each grapheme of `s` lands when wrapped at `width` cells,
+/// together with the offset right after the last one
+pub fn layout(s: &str, width: isize) -> (Vec<(Point, &str)>, Point) {
+    let mut offset = Point::default();
+    let positions = graphemes(s)
+        .map(|g| {
+            let newline = is_newline(g);
+            let cells = cells(g);
+            if offset.x + cells > width && offset.x > 0 {
+                offset.y += 1;
+                offset.x = 0;
+            }
+            let position = offset;
+            if newline {
+                offset.y += 1;
+                offset.x = 0;
+            } else {
+                offset.x += cells;
+            }
+            (position, g)
+        })
+        .collect();
+    (positions, offset)
+}
+
+/// Cells taken up by `g`, counting a newline as the one cell its cursor needs
+fn cells(g: &str) -> isize {
+    if is_newline(g) { 1 } else { width(g) as isize }
+}
+
+/// Index of the grapheme displayed at `point` when `s` is wrapped at `width` cells.
+/// Points past the end of a line map to the end of that line.
+pub fn index_at(s: &str, width: isize, point: Point) -> usize {
+    let (positions, _) = layout(s, width);
+    let count = positions.len();
+    positions
+        .into_iter()
+        .enumerate()
+        .find_map(|(i, (position, g))| {
+            let on_line = position.y == point.y;
+            ((on_line && (point.x < position.x + cells(g) || is_newline(g)))
+                || position.y > point.y)
+                .then_some(i)
+        })
+        .unwrap_or(count)
 }
 
 impl Element for StringElement {
     fn draw(&self, constraint: Size, display_list: &mut DisplayList) {
-        let mut offset = Point::default();
-        for (i, c) in self.s.chars().enumerate() {
-            if c != '\n' {
-                display_list.0.push(Operation::PutChar(c));
+        let (positions, mut end) = layout(&self.s, constraint.x);
+        let count = positions.len();
+        display_list.0.push(Operation::PushStyle(self.style));
+        for (i, (position, g)) in positions.into_iter().enumerate() {
+            if position.y >= constraint.y {
+                display_list.0.push(Operation::MoveTo(Point {
+                    x: constraint.x - 1,
+                    y: constraint.y - 1,
+                }));
+                display_list.0.push(Operation::PutChar('…'));
+                display_list.0.push(Operation::PopStyle);
+                return;
             }
-            if let Some(cursor) = self.cursor
-                && cursor == i
-            {
-                if c == '\n' {
+            display_list.0.push(Operation::MoveTo(position));
+            let newline = is_newline(g);
+            let selected = self
+                .selection
+                .as_ref()
+                .is_some_and(|selection| selection.contains(&i));
+            if selected {
+                display_list
+                    .0
+                    .push(Operation::PushStyle(Style::default().reverse()));
+            }
+            if newline {
+                // Shows selected line breaks
+                if selected {
                     display_list.0.push(Operation::PutChar(' '));
-                    display_list.0.push(Operation::DrawCursor);
-                } else {
-                    display_list.0.push(Operation::DrawCursor);
                 }
-            }
-            if c == '\n' {
-                offset.y += 1;
-                offset.x = 0;
             } else {
-                offset.x += 1;
+                let mut chars = g.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => display_list.0.push(Operation::PutChar(c)),
+                    _ => display_list.0.push(Operation::PutGrapheme(g.to_string())),
+                }
             }
-            if offset.x >= constraint.x {
-                offset.y += 1;
-                offset.x = 0;
-                if offset.y >= constraint.y {
-                    display_list.0.push(Operation::PutChar('…'));
-                    break;
+            if selected {
+                display_list.0.push(Operation::PopStyle);
+            }
+            if let Some(cursor) = self.cursor
+                && cursor == i
+            {
+                if newline {
+                    display_list.0.push(Operation::PutChar(' '));
                 }
+                display_list.0.push(Operation::DrawCursor);
             }
-            display_list.0.push(Operation::MoveTo(offset));
         }
         if let Some(cursor) = self.cursor
-            && cursor == self.s.len()
+            && cursor == count
         {
+            if end.x >= constraint.x {
+                end.y += 1;
+                end.x = 0;
+            }
+            display_list.0.push(Operation::MoveTo(end));
             display_list.0.push(Operation::DrawCursor);
         }
+        display_list.0.push(Operation::PopStyle);
+    }
+    fn size(&self, constraint: Size) -> Size {
+        let (positions, end) = layout(&self.s, constraint.x);
+        let mut widest = positions
+            .iter()
+            .map(|(position, g)| position.x + cells(g))
+            .max()
+            .unwrap_or(0);
+        if self.cursor == Some(positions.len()) {
+            widest = widest.max(end.x + 1);
+        }
+        Size {
+            x: widest.min(constraint.x),
+            y: (end.y + 1).min(constraint.y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::frame::{Frame, Token};
+
+    fn draw(s: &str, cursor: Option<usize>, width: usize, height: usize) -> Frame {
+        draw_selected(s, cursor, None, width, height)
+    }
+
+    fn draw_selected(
+        s: &str,
+        cursor: Option<usize>,
+        selection: Option<Range<usize>>,
+        width: usize,
+        height: usize,
+    ) -> Frame {
+        let mut display_list = DisplayList::default();
+        StringElement {
+            s: s.to_string(),
+            cursor,
+            selection,
+            style: Style::default(),
+        }
+        .draw(
+            Size {
+                x: width as isize,
+                y: height as isize,
+            },
+            &mut display_list,
+        );
+        let mut frame = vec![vec![Token::default(); width]; height];
+        _ = display_list.draw_on(&mut frame);
+        frame
+    }
+
+    fn plain(c: char) -> Token {
+        Token::Char(c, Style::default())
+    }
+
+    #[test]
+    fn wide_glyphs_take_two_cells() {
+        draw("中a", None, 4, 1).must_be(vec![vec![
+            plain('中'),
+            Token::Continuation,
+            plain('a'),
+            plain(' '),
+        ]]);
+    }
+
+    #[test]
+    fn clusters_stay_together() {
+        draw("e\u{301}⚪️x", None, 4, 1).must_be(vec![vec![
+            Token::Cluster("e\u{301}".into(), Style::default()),
+            Token::Cluster("⚪️".into(), Style::default()),
+            Token::Continuation,
+            plain('x'),
+        ]]);
+    }
+
+    #[test]
+    fn wraps_by_display_width() {
+        draw("a中b", None, 2, 3).must_be(vec![
+            vec![plain('a'), plain(' ')],
+            vec![plain('中'), Token::Continuation],
+            vec![plain('b'), plain(' ')],
+        ]);
+    }
+
+    #[test]
+    fn truncates_with_ellipsis() {
+        draw("abc中", None, 2, 2).must_be(vec![
+            vec![plain('a'), plain('b')],
+            vec![plain('c'), plain('…')],
+        ]);
+    }
+
+    #[test]
+    fn cursor_counts_graphemes() {
+        let frame = draw("中é", Some(1), 4, 1);
+        frame[0][2]
+            .style()
+            .and_then(|style| style.bg)
+            .is_some()
+            .must_be(true);
+    }
+
+    #[test]
+    fn selections_are_reversed() {
+        let frame = draw_selected("a中\nb", None, Some(1..3), 4, 2);
+        let reversed = |x: usize, y: usize| frame[y][x].style().is_some_and(|style| style.reverse);
+        [
+            reversed(0, 0),
+            reversed(1, 0),
+            reversed(3, 0),
+            reversed(0, 1),
+        ]
+        .must_be([false, true, true, false]);
+        frame[0][1]
+            .clone()
+            .must_be(Token::Char('中', Style::default().reverse()));
     }
 }
@@ -0,0 +1,288 @@
+use std::sync::Arc;
+
+use crate::{
+    prelude::{DisplayList, Element, Operation, Point, Size, StringElement, Style},
+    unicode::{truncate, width},
+};
+
+pub mod prelude {
+    pub use super::{Alignment, ColumnWidth, SortOrder, TableColumn, TableElement};
+}
+
+/// How wide a table column is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColumnWidth {
+    /// Exactly this many cells
+    Fixed(isize),
+    /// This percentage of the table's width
+    Percent(isize),
+    /// As wide as the widest cell
+    #[default]
+    Fit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Alignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct TableColumn {
+    pub header: String,
+    pub width: ColumnWidth,
+    pub align: Alignment,
+}
+
+impl TableColumn {
+    pub fn new(header: impl Into<String>) -> Self {
+        Self {
+            header: header.into(),
+            ..Default::default()
+        }
+    }
+    pub fn width(self, width: ColumnWidth) -> Self {
+        Self { width, ..self }
+    }
+    pub fn align(self, align: Alignment) -> Self {
+        Self { align, ..self }
+    }
+}
+
+impl From<&str> for TableColumn {
+    fn from(header: &str) -> Self {
+        Self::new(header)
+    }
+}
+
+impl From<String> for TableColumn {
+    fn from(header: String) -> Self {
+        Self::new(header)
+    }
+}
+
+/// A header row over `rows`, shown in `order`, with the row at `cursor` kept in view.
+/// They are shared with the widget, which keeps them between frames.
+pub struct TableElement {
+    pub columns: Arc<Vec<TableColumn>>,
+    pub rows: Arc<Vec<Vec<String>>>,
+    /// Indices into `rows` in the order they are shown
+    pub order: Arc<Vec<usize>>,
+    /// Position in `order` of the cursor row
+    pub cursor: usize,
+    /// Shows the row at `cursor` reversed
+    pub highlight: bool,
+    /// Marks the header of the column the rows are sorted by
+    pub sort: Option<(usize, SortOrder)>,
+}
+
+const GAP: isize = 1;
+
+/// Index of the first row shown when `visible` rows fit and the one at `cursor` must be among them
+pub fn first_visible(cursor: usize, visible: isize) -> usize {
+    cursor.saturating_sub((visible - 1).max(0) as usize)
+}
+
+fn align(s: &str, cells: isize, align: Alignment) -> String {
+    let s = truncate(s, cells.max(0) as usize);
+    let free = (cells - width(&s) as isize).max(0) as usize;
+    let (before, after) = match align {
+        Alignment::Left => (0, free),
+        Alignment::Center => (free / 2, free - free / 2),
+        Alignment::Right => (free, 0),
+    };
+    format!("{}{s}{}", " ".repeat(before), " ".repeat(after))
+}
+
+impl TableElement {
+    fn header(&self, column: usize) -> String {
+        let header = &self.columns[column].header;
+        match self.sort {
+            Some((sorted, SortOrder::Ascending)) if sorted == column => format!("{header} ▲"),
+            Some((sorted, SortOrder::Descending)) if sorted == column => format!("{header} ▼"),
+            _ => header.clone(),
+        }
+    }
+
+    fn natural_width(&self, column: usize) -> isize {
+        self.rows
+            .iter()
+            .filter_map(|row| row.get(column))
+            .map(|cell| width(cell))
+            .chain([width(&self.header(column))])
+            .max()
+            .unwrap_or(0) as isize
+    }
+
+    fn gaps(&self) -> isize {
+        GAP * (self.columns.len() as isize - 1).max(0)
+    }
+
+    /// Width each column asks for when the table is `total` cells wide
+    fn wanted(&self, total: isize) -> Vec<isize> {
+        let available = (total - self.gaps()).max(0);
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| match column.width {
+                ColumnWidth::Fixed(cells) => cells.max(0),
+                ColumnWidth::Percent(percent) => available * percent / 100,
+                ColumnWidth::Fit => self.natural_width(i),
+            })
+            .collect()
+    }
+
+    /// Width of each column when the table is `total` cells wide. Columns that do not fit are
+    /// narrowed starting from the last one.
+    fn widths(&self, total: isize) -> Vec<isize> {
+        let available = (total - self.gaps()).max(0);
+        let mut widths = self.wanted(total);
+        let mut excess = widths.iter().sum::<isize>() - available;
+        for width in widths.iter_mut().rev() {
+            if excess <= 0 {
+                break;
+            }
+            let cut = excess.min(*width);
+            *width -= cut;
+            excess -= cut;
+        }
+        widths
+    }
+
+    fn line<'a>(&self, widths: &[isize], cells: impl Iterator<Item = &'a str>) -> String {
+        cells
+            .zip(widths)
+            .zip(self.columns.iter())
+            .map(|((cell, &cells), column)| align(cell, cells, column.align))
+            .collect::<Vec<_>>()
+            .join(&" ".repeat(GAP as usize))
+    }
+}
+
+fn draw_line(s: String, style: Style, y: isize, width: isize, display_list: &mut DisplayList) {
+    let anchor = Point { x: 0, y };
+    display_list.0.push(Operation::SetAnchor(anchor));
+    StringElement {
+        s,
+        cursor: None,
+        selection: None,
+        style,
+    }
+    .draw(Size { x: width, y: 1 }, display_list);
+    display_list.0.push(Operation::SetAnchor(-anchor));
+}
+
+impl Element for TableElement {
+    fn draw(&self, constraint: Size, display_list: &mut DisplayList) {
+        if constraint.y <= 0 {
+            return;
+        }
+        let widths = self.widths(constraint.x);
+        let headers = (0..self.columns.len())
+            .map(|i| self.header(i))
+            .collect::<Vec<_>>();
+        let header = self.line(&widths, headers.iter().map(String::as_str));
+        draw_line(
+            header,
+            Style::default().bold(),
+            0,
+            constraint.x,
+            display_list,
+        );
+        let visible = constraint.y - 1;
+        let first = first_visible(self.cursor, visible);
+        for (y, (index, &row)) in (1..=visible).zip(self.order.iter().enumerate().skip(first)) {
+            let line = self.line(&widths, self.rows[row].iter().map(String::as_str));
+            let style = if self.highlight && self.cursor == index {
+                Style::default().reverse()
+            } else {
+                Style::default()
+            };
+            draw_line(line, style, y, constraint.x, display_list);
+        }
+    }
+    fn size(&self, constraint: Size) -> Size {
+        let natural = self.wanted(constraint.x).iter().sum::<isize>() + self.gaps();
+        Size {
+            x: natural.min(constraint.x),
+            y: (self.order.len() as isize + 1).min(constraint.y),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+
+    fn table(columns: Vec<TableColumn>) -> TableElement {
+        TableElement {
+            columns: Arc::new(columns),
+            rows: Arc::new(vec![
+                vec!["apple".to_string(), "3".to_string()],
+                vec!["fig".to_string(), "12".to_string()],
+            ]),
+            order: Arc::new(vec![0, 1]),
+            cursor: 0,
+            highlight: false,
+            sort: None,
+        }
+    }
+
+    #[test]
+    fn widths_follow_their_policies() {
+        table(vec!["name".into(), "n".into()])
+            .widths(20)
+            .must_be(vec![5, 2]);
+        table(vec![
+            TableColumn::new("name").width(ColumnWidth::Percent(50)),
+            TableColumn::new("n").width(ColumnWidth::Fixed(4)),
+        ])
+        .widths(21)
+        .must_be(vec![10, 4]);
+    }
+
+    #[test]
+    fn percentages_ask_for_their_share_only() {
+        let halves = table(vec![
+            TableColumn::new("name").width(ColumnWidth::Percent(50)),
+            TableColumn::new("n").width(ColumnWidth::Percent(50)),
+        ]);
+        halves
+            .size(Size { x: 21, y: 5 })
+            .must_be(Size { x: 21, y: 3 });
+        let half = table(vec![
+            TableColumn::new("name").width(ColumnWidth::Percent(50)),
+            "n".into(),
+        ]);
+        half.size(Size { x: 41, y: 5 })
+            .must_be(Size { x: 23, y: 3 });
+    }
+
+    #[test]
+    fn overflowing_columns_shrink_from_the_right() {
+        table(vec!["name".into(), "n".into()])
+            .widths(6)
+            .must_be(vec![5, 0]);
+        table(vec!["name".into(), "n".into()])
+            .widths(4)
+            .must_be(vec![3, 0]);
+    }
+
+    #[test]
+    fn cells_are_aligned_and_truncated() {
+        align("ab", 5, Alignment::Left).must_be("ab   ");
+        align("ab", 5, Alignment::Center).must_be(" ab  ");
+        align("ab", 5, Alignment::Right).must_be("   ab");
+        align("abcdef", 4, Alignment::Right).must_be("abc…");
+    }
+}
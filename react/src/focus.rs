@@ -0,0 +1,189 @@
+use std::cell::RefCell;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use stdext::prelude::switch;
+
+use crate::{component::prelude::*, message::prelude::*, tree};
+
+pub mod prelude {
+    pub use super::{Blurred, Focused, focusable, is_focused};
+}
+
+/// Sent when the widget with this id gains keyboard focus
+pub struct Focused(pub usize);
+/// Sent when the widget with this id loses keyboard focus
+pub struct Blurred(pub usize);
+
+#[derive(Debug, Default)]
+struct FocusRing {
+    focused: Option<usize>,
+    /// Focusable widgets in tree order, as registered during the last pass
+    ring: Vec<usize>,
+}
+
+thread_local! {
+    static FOCUS: RefCell<FocusRing> = RefCell::new(FocusRing::default());
+}
+
+/// Makes `component` take part in Tab/Shift-Tab focus traversal
+pub fn focusable(component: Component) -> Component {
+    component.borrow_mut().set_focusable(true);
+    component
+}
+
+pub fn focused() -> Option<usize> {
+    FOCUS.with_borrow(|focus| focus.focused)
+}
+
+pub fn is_focused(id: usize) -> bool {
+    focused() == Some(id)
+}
+
+/// Moves focus to `id`, sending `Blurred` and `Focused` if it changed
+pub fn focus(id: usize) {
+    let prev = FOCUS.with_borrow_mut(|focus| focus.focused.replace(id));
+    if prev != Some(id) {
+        if let Some(prev) = prev {
+            send(Blurred(prev));
+        }
+        send(Focused(id));
+    }
+}
+
+/// Focuses `id` or, failing that, its closest focusable ancestor
+pub(crate) fn focus_nearest(id: usize) {
+    let nearest = FOCUS.with_borrow(|focus| {
+        tree::ancestors(id)
+            .into_iter()
+            .find(|id| focus.ring.contains(id))
+    });
+    if let Some(nearest) = nearest {
+        focus(nearest);
+    }
+}
+
+pub(crate) fn begin() {
+    FOCUS.with_borrow_mut(|focus| focus.ring.clear());
+}
+
+pub(crate) fn register(id: usize) {
+    FOCUS.with_borrow_mut(|focus| focus.ring.push(id));
+}
+
+/// Keeps focus on a mounted widget, falling back to the first focusable one
+pub(crate) fn settle() {
+    let (focused, is_mounted, first) = FOCUS.with_borrow(|focus| {
+        (
+            focus.focused,
+            focus.focused.is_some_and(|id| focus.ring.contains(&id)),
+            focus.ring.first().copied(),
+        )
+    });
+    match (focused, first) {
+        _ if is_mounted => {}
+        (_, Some(first)) => focus(first),
+        (Some(focused), None) => {
+            FOCUS.with_borrow_mut(|focus| focus.focused = None);
+            send(Blurred(focused));
+        }
+        (None, None) => {}
+    }
+}
+
+fn step(forward: bool) {
+    let next = FOCUS.with_borrow(|focus| {
+        let len = focus.ring.len();
+        let current = focus
+            .focused
+            .and_then(|focused| focus.ring.iter().position(|&id| id == focused));
+        let next = match (current, forward) {
+            (Some(i), true) => (i + 1) % len,
+            (Some(i), false) => (i + len - 1) % len,
+            (None, true) => 0,
+            (None, false) => len - 1,
+        };
+        focus.ring[next]
+    });
+    focus(next);
+}
+
+/// Handles Tab and Shift-Tab. Returns whether `msg` was used up moving focus.
+/// With fewer than two focusable widgets there is nowhere to move, so Tab is left to them.
+pub(crate) fn traverse(msg: &Message) -> bool {
+    if FOCUS.with_borrow(|focus| focus.ring.len()) < 2 {
+        return false;
+    }
+    switch(msg)
+        .case(|event: &KeyEvent| match (event.modifiers, event.code) {
+            (KeyModifiers::NONE, KeyCode::Tab) => {
+                step(true);
+                true
+            }
+            (_, KeyCode::BackTab) => {
+                step(false);
+                true
+            }
+            _ => false,
+        })
+        .default(|| false)
+}
+
+/// Whether key events should reach `id`: it is on the path from the root to the focused widget,
+/// or inside the focused widget. Everything receives keys while nothing is focused.
+pub(crate) fn receives_keys(id: usize) -> bool {
+    match focused() {
+        Some(focused) => tree::is_ancestor(id, focused) || tree::is_ancestor(focused, id),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::{Harness, Point, Size, column, counter, text_field};
+
+    fn two_fields() -> Harness {
+        Harness::mount(
+            column([text_field("").0, text_field("").0]),
+            Size { x: 10, y: 2 },
+        )
+    }
+
+    #[test]
+    fn first_focusable_gets_focus() {
+        let mut harness = two_fields();
+        harness.cursor().must_be(Some(Point { x: 0, y: 0 }));
+        harness.type_str("a");
+        harness.text().must_be("a");
+    }
+
+    #[test]
+    fn tab_moves_keys_to_the_next_field() {
+        let mut harness = two_fields();
+        harness.type_str("a").press(KeyCode::Tab).type_str("b");
+        harness.lines().must_be(vec!["a", "b"]);
+        harness.cursor().must_be(Some(Point { x: 1, y: 1 }));
+        harness.press(KeyCode::BackTab).type_str("c");
+        harness.lines().must_be(vec!["ac", "b"]);
+    }
+
+    #[test]
+    fn tab_wraps_around() {
+        let mut harness = two_fields();
+        harness
+            .press(KeyCode::Tab)
+            .press(KeyCode::Tab)
+            .type_str("a");
+        harness.lines().must_be(vec!["a", ""]);
+    }
+
+    #[test]
+    fn unfocused_widgets_miss_keys() {
+        let mut harness =
+            Harness::mount(column([counter(0), text_field("").0]), Size { x: 10, y: 2 });
+        harness.type_str("+");
+        harness.lines().must_be(vec!["0", "+"]);
+    }
+}
@@ -1,33 +1,85 @@
 use std::fmt::Display;
 
+use unicode_width::UnicodeWidthChar;
+
+use crate::{style::Style, unicode::width};
+
 pub mod prelude {
     pub use super::{Frame, FrameExt};
 }
 
-#[derive(Debug, Clone)]
-/// All Tokens must take up exactly one space in a terminal
+#[derive(Debug, Clone, PartialEq)]
+/// All Tokens take up exactly one space in a terminal.
+/// A wide glyph is the Token in its first cell followed by a `Continuation` in the second.
 pub enum Token {
-    Char(char),
-    AnnotatedChar(&'static str, char, &'static str),
+    Char(char, Style),
+    /// A grapheme cluster made of several chars, such as an emoji with a modifier
+    Cluster(Box<str>, Style),
+    Continuation,
+}
+
+impl Default for Token {
+    fn default() -> Self {
+        Token::Char(' ', Style::default())
+    }
+}
+
+impl Token {
+    pub fn grapheme(grapheme: &str, style: Style) -> Self {
+        let mut chars = grapheme.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Token::Char(c, style),
+            _ => Token::Cluster(grapheme.into(), style),
+        }
+    }
+    pub fn style(&self) -> Option<Style> {
+        match self {
+            Token::Char(_, style) | Token::Cluster(_, style) => Some(*style),
+            Token::Continuation => None,
+        }
+    }
+    pub fn style_mut(&mut self) -> Option<&mut Style> {
+        match self {
+            Token::Char(_, style) | Token::Cluster(_, style) => Some(style),
+            Token::Continuation => None,
+        }
+    }
+    /// Number of cells this token's glyph covers, counting its continuation
+    pub fn width(&self) -> usize {
+        match self {
+            Token::Char(c, _) => c.width().unwrap_or(1),
+            Token::Cluster(s, _) => width(s),
+            Token::Continuation => 0,
+        }
+    }
 }
 
 pub trait TokensExt {
+    /// Appends the tokens to `s`, switching styles from `current` only where adjacent cells differ
+    fn write_styled(&self, current: &mut Style, s: &mut String);
     fn to_string(&self) -> String;
 }
 
-impl TokensExt for Vec<Token> {
-    fn to_string(&self) -> String {
-        let mut s = String::new();
+impl TokensExt for [Token] {
+    fn write_styled(&self, current: &mut Style, s: &mut String) {
         for token in self {
+            let Some(style) = token.style() else {
+                continue;
+            };
+            s.push_str(&current.transition(&style));
+            *current = style;
             match token {
-                Token::Char(c) => s.push(*c),
-                Token::AnnotatedChar(s1, c, s2) => {
-                    s.push_str(s1);
-                    s.push(*c);
-                    s.push_str(s2);
-                }
+                Token::Char(c, _) => s.push(*c),
+                Token::Cluster(cluster, _) => s.push_str(cluster),
+                Token::Continuation => {}
             }
         }
+    }
+    fn to_string(&self) -> String {
+        let mut s = String::new();
+        let mut current = Style::default();
+        self.write_styled(&mut current, &mut s);
+        s.push_str(&current.transition(&Style::default()));
         s
     }
 }
@@ -56,7 +108,7 @@ impl FrameExt for Frame {
         let max_width = self.max_width();
         for row in self.iter_mut() {
             let diff = max_width - row.len();
-            row.append(&mut vec![Token::Char(' '); diff]);
+            row.append(&mut vec![Token::default(); diff]);
         }
     }
     fn expand_to_height(&mut self, target: usize) {
@@ -64,7 +116,7 @@ impl FrameExt for Frame {
         let diff = target - self.height();
         if diff > 0 {
             for _ in 0..diff {
-                self.push(vec![Token::Char(' '); width]);
+                self.push(vec![Token::default(); width]);
             }
         }
     }
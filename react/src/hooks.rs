@@ -0,0 +1,431 @@
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use crate::{
+    component::prelude::*,
+    context::Dependency,
+    message::{Message, MessageFlow},
+    widget::Widget,
+};
+
+pub mod prelude {
+    pub use super::{StateHandle, use_effect, use_memo, use_message, use_state};
+}
+
+type Handler = Rc<dyn Fn(&Message) -> MessageFlow>;
+
+/// The hook slots of one widget, filled in the order its builder calls the hooks
+#[derive(Default)]
+pub(crate) struct Hooks {
+    slots: RefCell<Vec<Box<dyn Any>>>,
+    cursor: Cell<usize>,
+    /// Set by `StateHandle`s so the widget rebuilds
+    dirty: Rc<Cell<bool>>,
+    handlers: RefCell<Vec<Handler>>,
+    /// Effects whose dependencies changed, run once the builder returns
+    effects: RefCell<Vec<Box<dyn FnOnce()>>>,
+    /// The context values read during the last build
+    contexts: RefCell<Vec<Dependency>>,
+}
+
+thread_local! {
+    /// The hooks of the widgets whose builders are running, innermost last
+    static BUILDING: RefCell<Vec<Rc<Hooks>>> = const { RefCell::new(vec![]) };
+    /// Whether hook state changed since the current update round began
+    static CHANGED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Starts watching for hook state changes, at the start of an update round
+pub(crate) fn begin() {
+    CHANGED.set(false);
+}
+
+/// Whether hook state changed since `begin`, e.g. in an effect after its widget was built,
+/// so another round has to build again
+pub(crate) fn changed() -> bool {
+    CHANGED.get()
+}
+
+impl Hooks {
+    /// Runs `builder` with these hooks as the ones its hook calls use
+    pub(crate) fn build<T>(self: &Rc<Self>, builder: impl FnOnce() -> T) -> T {
+        self.cursor.set(0);
+        self.dirty.set(false);
+        self.handlers.borrow_mut().clear();
+        self.contexts.borrow_mut().clear();
+        BUILDING.with_borrow_mut(|building| building.push(self.clone()));
+        let built = builder();
+        BUILDING.with_borrow_mut(|building| building.pop());
+        let effects = std::mem::take(&mut *self.effects.borrow_mut());
+        for effect in effects {
+            effect();
+        }
+        built
+    }
+    /// Drops every slot, running the cleanups of effects
+    pub(crate) fn clear(&self) {
+        let slots = std::mem::take(&mut *self.slots.borrow_mut());
+        drop(slots);
+        self.handlers.borrow_mut().clear();
+        self.dirty.set(true);
+    }
+    /// Whether state changed or a context value read during the last build is now different
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.dirty.get() || self.contexts.borrow().iter().any(Dependency::is_stale)
+    }
+    /// Passes `msg` to the `use_message` handlers, intercepting it if any of them does
+    pub(crate) fn on_message(&self, msg: &Message) -> MessageFlow {
+        let handlers = self.handlers.borrow().clone();
+        let mut flow = MessageFlow::Propagate;
+        for handler in handlers {
+            if let MessageFlow::Intercept = handler(msg) {
+                flow = MessageFlow::Intercept;
+            }
+        }
+        flow
+    }
+}
+
+fn building() -> Rc<Hooks> {
+    BUILDING
+        .with_borrow(|building| building.last().cloned())
+        .expect("Hooks can only be used while a widget is being built")
+}
+
+/// Records that the widget being built read a context value
+pub(crate) fn depend(dependency: Dependency) {
+    building().contexts.borrow_mut().push(dependency);
+}
+
+/// The slot for the next hook call of the widget being built, created by `init` on first use
+fn slot<T: 'static>(init: impl FnOnce() -> T, f: impl FnOnce(&mut T, &Hooks)) {
+    let hooks = building();
+    let index = hooks.cursor.get();
+    hooks.cursor.set(index + 1);
+    let mut slots = hooks.slots.borrow_mut();
+    if index == slots.len() {
+        slots.push(Box::new(init()));
+    }
+    let slot = slots[index]
+        .downcast_mut::<T>()
+        .expect("Hooks must be called in the same order on every build");
+    f(slot, &hooks);
+}
+
+/// State kept by a widget across rebuilds. Changing it rebuilds the widget.
+pub struct StateHandle<T> {
+    value: Rc<RefCell<T>>,
+    dirty: Rc<Cell<bool>>,
+}
+
+impl<T> Clone for StateHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            dirty: self.dirty.clone(),
+        }
+    }
+}
+
+impl<T> StateHandle<T> {
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.value.borrow().clone()
+    }
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.value.borrow())
+    }
+    pub fn set(&self, value: T) {
+        self.update(|state| *state = value);
+    }
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        f(&mut self.value.borrow_mut());
+        self.dirty.set(true);
+        CHANGED.set(true);
+    }
+}
+
+/// State for the widget being built, starting out as `init()`
+pub fn use_state<T: 'static>(init: impl FnOnce() -> T) -> StateHandle<T> {
+    let mut handle = None;
+    slot(
+        || Rc::new(RefCell::new(init())),
+        |value: &mut Rc<RefCell<T>>, hooks| {
+            handle = Some(StateHandle {
+                value: value.clone(),
+                dirty: hooks.dirty.clone(),
+            })
+        },
+    );
+    handle.expect("The slot was just filled")
+}
+
+/// `compute(&deps)`, only recomputed when `deps` change between builds
+pub fn use_memo<D: PartialEq + 'static, T: Clone + 'static>(
+    deps: D,
+    compute: impl FnOnce(&D) -> T,
+) -> T {
+    let mut memo = None;
+    let mut compute = Some(compute);
+    slot(
+        || None::<(D, T)>,
+        |slot, _| {
+            if slot.as_ref().is_none_or(|(old, _)| *old != deps) {
+                let compute = compute.take().expect("Computed once per build");
+                let value = compute(&deps);
+                *slot = Some((deps, value));
+            }
+            memo = slot.as_ref().map(|(_, value)| value.clone());
+        },
+    );
+    memo.expect("The slot was just filled")
+}
+
+/// The cleanup returned by the last run of an effect, run before the next one or when the
+/// widget goes away
+#[derive(Default)]
+struct Cleanup(RefCell<Option<Box<dyn FnOnce()>>>);
+
+impl Cleanup {
+    fn run(&self) {
+        if let Some(cleanup) = self.0.take() {
+            cleanup();
+        }
+    }
+}
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        self.run();
+    }
+}
+
+/// Runs `effect` after the first build and after every build where `deps` changed, first
+/// running the cleanup it returned last time
+pub fn use_effect<D: PartialEq + 'static, C: FnOnce() + 'static>(
+    deps: D,
+    effect: impl FnOnce() -> C + 'static,
+) {
+    slot(
+        || (None::<D>, Rc::new(Cleanup::default())),
+        |(old, cleanup), hooks| {
+            if old.as_ref() == Some(&deps) {
+                return;
+            }
+            *old = Some(deps);
+            let cleanup = cleanup.clone();
+            hooks.effects.borrow_mut().push(Box::new(move || {
+                cleanup.run();
+                *cleanup.0.borrow_mut() = Some(Box::new(effect()));
+            }));
+        },
+    );
+}
+
+/// Handles messages of type `T` reaching the widget being built, before its own `on_message`.
+/// The handler is replaced on every build, so it can capture the current state.
+pub fn use_message<T: 'static>(handler: impl Fn(&T) -> MessageFlow + 'static) {
+    slot(
+        || (),
+        |_, hooks| {
+            hooks
+                .handlers
+                .borrow_mut()
+                .push(Rc::new(move |msg: &Message| {
+                    match msg.downcast_ref::<T>() {
+                        Some(msg) => handler(msg),
+                        None => MessageFlow::Propagate,
+                    }
+                }))
+        },
+    );
+}
+
+impl Widget<()> {
+    /// A widget whose state lives in the hooks its builder calls
+    pub fn function<B: Fn() -> Component + 'static>(builder: B) -> Component {
+        Widget::stateful((), |_, _| MessageFlow::Propagate, move |_| builder())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crossterm::event::{KeyCode, KeyEvent};
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::{
+        message::prelude::*,
+        prelude::{Harness, Size, column, text, text_field},
+    };
+
+    fn counter() -> Component {
+        Widget::function(|| {
+            let count = use_state(|| 0);
+            use_message({
+                let count = count.clone();
+                move |event: &KeyEvent| match event.code {
+                    KeyCode::Char('+') => {
+                        count.update(|count| *count += 1);
+                        Intercept
+                    }
+                    _ => Propagate,
+                }
+            });
+            text(count.get().to_string())
+        })
+    }
+
+    #[test]
+    fn state_survives_rebuilds() {
+        let mut harness = Harness::mount(counter(), Size { x: 3, y: 1 });
+        harness.lines().must_be(vec!["0"]);
+        harness.type_str("++");
+        harness.lines().must_be(vec!["2"]);
+    }
+
+    #[test]
+    fn state_set_by_effects_shows_in_the_same_update() {
+        let harness = Harness::mount(
+            Widget::function(|| {
+                let ready = use_state(|| false);
+                let set = ready.clone();
+                use_effect((), move || {
+                    set.set(true);
+                    || {}
+                });
+                text(if ready.get() { "yes" } else { "no" })
+            }),
+            Size { x: 3, y: 1 },
+        );
+        harness.lines().must_be(vec!["yes"]);
+    }
+
+    #[test]
+    fn state_survives_rebuilds_of_the_parent() {
+        let mut harness = Harness::mount(
+            Widget::stateful(
+                0,
+                |this, msg| {
+                    switch(msg).case(|event: &KeyEvent| {
+                        if event.code == KeyCode::Char('x') {
+                            this.set_state(|rebuilds| *rebuilds += 1)
+                        }
+                    });
+                    Propagate
+                },
+                |rebuilds| column([text(rebuilds.to_string()), counter()]),
+            ),
+            Size { x: 3, y: 2 },
+        );
+        harness.type_str("++x");
+        harness.lines().must_be(vec!["1", "2"]);
+    }
+
+    #[test]
+    fn memos_recompute_when_their_deps_change() {
+        let computed = Rc::new(RefCell::new(vec![]));
+        let log = computed.clone();
+        let mut harness = Harness::mount(
+            Widget::function(move || {
+                let count = use_state(|| 0);
+                use_message({
+                    let count = count.clone();
+                    move |_: &KeyEvent| {
+                        count.update(|count| *count += 1);
+                        Propagate
+                    }
+                });
+                let log = log.clone();
+                let half = use_memo(count.get() / 2, move |&half| {
+                    log.borrow_mut().push(half);
+                    half
+                });
+                text(half.to_string())
+            }),
+            Size { x: 3, y: 1 },
+        );
+        harness.type_str("abc");
+        harness.lines().must_be(vec!["1"]);
+        computed.borrow().clone().must_be(vec![0, 1]);
+    }
+
+    #[test]
+    fn effects_clean_up_before_rerunning_and_on_removal() {
+        let events = Rc::new(RefCell::new(vec![]));
+        let log = events.clone();
+        let watcher = move || {
+            let log = log.clone();
+            Widget::function(move || {
+                let count = use_state(|| 0);
+                use_message({
+                    let count = count.clone();
+                    move |_: &KeyEvent| {
+                        count.update(|count| *count += 1);
+                        Propagate
+                    }
+                });
+                let log = log.clone();
+                let current = count.get();
+                use_effect(current, move || {
+                    log.borrow_mut().push(format!("start {current}"));
+                    move || log.borrow_mut().push(format!("stop {current}"))
+                });
+                text(current.to_string())
+            })
+        };
+        let mut harness = Harness::mount(
+            Widget::stateful(
+                true,
+                move |this, msg| {
+                    if let Some(KeyEvent {
+                        code: KeyCode::Esc, ..
+                    }) = msg.downcast_ref()
+                    {
+                        this.set_state(|shown| *shown = false);
+                        return Intercept;
+                    }
+                    Propagate
+                },
+                move |&shown| if shown { watcher() } else { text("") },
+            ),
+            Size { x: 3, y: 1 },
+        );
+        harness.type_str("a");
+        harness.press(KeyCode::Esc);
+        events
+            .borrow()
+            .clone()
+            .must_be(vec!["start 0", "stop 0", "start 1", "stop 1"]);
+    }
+
+    #[test]
+    fn hooked_widgets_keep_the_components_they_make() {
+        let submitted = Rc::new(RefCell::new(vec![]));
+        let log = submitted.clone();
+        let mut harness = Harness::mount(
+            column([Widget::function(move || {
+                let (field, buffer) = use_memo((), |_| text_field(""));
+                let log = log.clone();
+                use_message(move |event: &KeyEvent| match event.code {
+                    KeyCode::Enter => {
+                        log.borrow_mut().push(buffer.borrow().clone());
+                        Intercept
+                    }
+                    _ => Propagate,
+                });
+                field
+            })]),
+            Size { x: 5, y: 1 },
+        );
+        harness.type_str("hi").press(KeyCode::Enter);
+        submitted.borrow().clone().must_be(vec!["hi"]);
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    cell::RefCell, collections::HashMap, error::Error, fmt, rc::Rc, str::FromStr, time::Duration,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::{component::prelude::*, focus};
+
+pub mod prelude {
+    pub use super::{Action, Keymap, QUIT, with_keymap};
+}
+
+/// How long to wait for the next key of a chord before giving up on it
+pub const CHORD_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Bound to Ctrl+C by default; stops `render`, whatever the focused widget binds
+pub const QUIT: &str = "quit";
+
+/// Sent along the path to the focused widget when the keys bound to this action are pressed,
+/// instead of the `KeyEvent`s themselves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Action(pub &'static str);
+
+/// A key together with its modifiers, such as Ctrl+S
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    /// Folds the different ways terminals report shifted characters into one:
+    /// letters are lowercase with SHIFT, other characters are taken as they are
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(c)
+                if c.is_alphabetic()
+                    && (c.is_uppercase() || modifiers.contains(KeyModifiers::SHIFT)) =>
+            {
+                Key {
+                    code: KeyCode::Char(c.to_lowercase().next().unwrap_or(c)),
+                    modifiers: modifiers | KeyModifiers::SHIFT,
+                }
+            }
+            KeyCode::Char(_) => Key {
+                code,
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            _ => Key { code, modifiers },
+        }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Key::new(event.code, event.modifiers)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidKey(pub String);
+
+impl fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid key: {}", self.0)
+    }
+}
+
+impl Error for InvalidKey {}
+
+impl FromStr for Key {
+    type Err = InvalidKey;
+
+    /// Parses keys like "Ctrl+Shift+Z", "Alt+Enter", "F5" or "?"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidKey(s.to_string());
+        // A trailing "+" is the plus key itself, as in "Ctrl++"
+        let (modifiers, name) = match s.strip_suffix('+') {
+            Some(rest) if rest.is_empty() || rest.ends_with('+') => (rest, "+"),
+            _ => s.rsplit_once('+').unwrap_or(("", s)),
+        };
+        let mut parsed = KeyModifiers::NONE;
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            parsed |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                "super" | "cmd" => KeyModifiers::SUPER,
+                _ => return Err(invalid()),
+            };
+        }
+        let code = match name.to_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            lower => match (
+                lower.strip_prefix('f').map(str::parse),
+                name.chars().count(),
+            ) {
+                (Some(Ok(n @ 1..=24)), _) => KeyCode::F(n),
+                // Letters are named in either case, Shift has to be spelled out
+                (_, 1) => KeyCode::Char(lower.chars().next().ok_or_else(invalid)?),
+                _ => return Err(invalid()),
+            },
+        };
+        Ok(Key::new(code, parsed))
+    }
+}
+
+/// Keys pressed one after the other, such as "Ctrl+K Ctrl+S"
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence(pub Vec<Key>);
+
+impl FromStr for KeySequence {
+    type Err = InvalidKey;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let keys = s
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.is_empty() {
+            return Err(InvalidKey(s.to_string()));
+        }
+        Ok(KeySequence(keys))
+    }
+}
+
+/// Named actions and the key sequences that trigger them by default
+#[derive(Debug, Clone, Default)]
+pub struct Keymap(Vec<(&'static str, KeySequence)>);
+
+impl Keymap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Binds `action` to `sequence`, which may be bound to more than one sequence.
+    /// Panics if `sequence` does not parse.
+    pub fn bind(mut self, action: &'static str, sequence: &str) -> Self {
+        let sequence = sequence
+            .parse()
+            .unwrap_or_else(|error| panic!("Cannot bind {action}: {error}"));
+        self.0.push((action, sequence));
+        self
+    }
+}
+
+/// Makes the actions in `keymap` available while `component` or one of its descendants has focus.
+/// The innermost widget wins when several bind the same keys, but none of them over `QUIT`.
+pub fn with_keymap(component: Component, keymap: Keymap) -> Component {
+    component.borrow_mut().set_keymap(keymap);
+    component
+}
+
+/// What a key press turned into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Input {
+    Key(KeyEvent),
+    Action(Action),
+}
+
+#[derive(Default)]
+struct Resolver {
+    /// Keymaps of the widgets built in the last pass, in tree order
+    scopes: Vec<(usize, Rc<Keymap>)>,
+    /// Sequences the app bound actions to instead of their defaults
+    overrides: HashMap<String, Vec<KeySequence>>,
+    /// The start of a chord, with when its last key was pressed
+    pending: Vec<KeyEvent>,
+    pressed: Duration,
+}
+
+thread_local! {
+    static RESOLVER: RefCell<Resolver> = RefCell::new(Resolver::default());
+    static GLOBAL: Rc<Keymap> = Rc::new(Keymap::new().bind(QUIT, "Ctrl+C"));
+}
+
+/// Binds `action` to `sequences` instead of the keys its keymaps declare.
+/// An empty list unbinds it.
+pub fn remap<'a>(
+    action: &str,
+    sequences: impl IntoIterator<Item = &'a str>,
+) -> Result<(), InvalidKey> {
+    let sequences = sequences
+        .into_iter()
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+    RESOLVER.with_borrow_mut(|resolver| resolver.overrides.insert(action.to_string(), sequences));
+    Ok(())
+}
+
+pub(crate) fn begin() {
+    RESOLVER.with_borrow_mut(|resolver| resolver.scopes.clear());
+}
+
+pub(crate) fn register(id: usize, keymap: Rc<Keymap>) {
+    RESOLVER.with_borrow_mut(|resolver| resolver.scopes.push((id, keymap)));
+}
+
+impl Resolver {
+    /// Bindings in effect for the focused widget. Quitting comes first so that it always works,
+    /// then the innermost keymaps.
+    fn bindings(&self) -> Vec<(&'static str, KeySequence)> {
+        let global = GLOBAL.with(Rc::clone);
+        let scopes = self
+            .scopes
+            .iter()
+            .rev()
+            .filter(|(id, _)| focus::receives_keys(*id))
+            .map(|(_, keymap)| keymap.clone());
+        let mut bindings = vec![];
+        for keymap in [global].into_iter().chain(scopes) {
+            let mut overridden = vec![];
+            for (action, sequence) in &keymap.0 {
+                match self.overrides.get(*action) {
+                    Some(sequences) if !overridden.contains(action) => {
+                        overridden.push(*action);
+                        bindings.extend(sequences.iter().map(|s| (*action, s.clone())));
+                    }
+                    Some(_) => {}
+                    None => bindings.push((*action, sequence.clone())),
+                }
+            }
+        }
+        bindings
+    }
+
+    /// Turns the pending keys into an action, or into plain keys when it is a single unbound one
+    fn flush(&mut self, bindings: &[(&'static str, KeySequence)]) -> Vec<Input> {
+        let pending = std::mem::take(&mut self.pending);
+        let keys = pending.iter().copied().map(Key::from).collect::<Vec<_>>();
+        match bindings.iter().find(|(_, sequence)| sequence.0 == keys) {
+            Some((action, _)) => vec![Input::Action(Action(action))],
+            // An unfinished or unbound chord is dropped
+            None if pending.len() > 1 => vec![],
+            None => pending.into_iter().map(Input::Key).collect(),
+        }
+    }
+}
+
+/// Feeds a key press pressed at `now` to the resolver. Keys that start a longer binding are held
+/// back until it completes, something else is pressed or `CHORD_TIMEOUT` passes.
+pub(crate) fn resolve(event: KeyEvent, now: Duration) -> Vec<Input> {
+    let mut inputs = expire(now);
+    RESOLVER.with_borrow_mut(|resolver| {
+        resolver.pending.push(event);
+        resolver.pressed = now;
+        let bindings = resolver.bindings();
+        let keys = resolver
+            .pending
+            .iter()
+            .copied()
+            .map(Key::from)
+            .collect::<Vec<_>>();
+        let continues = bindings
+            .iter()
+            .any(|(_, sequence)| sequence.0.len() > keys.len() && sequence.0.starts_with(&keys));
+        if !continues {
+            inputs.extend(resolver.flush(&bindings));
+        }
+    });
+    inputs
+}
+
+/// When `expire` will give up on the chord being typed, if there is one
+pub(crate) fn deadline() -> Option<Duration> {
+    RESOLVER.with_borrow(|resolver| {
+        (!resolver.pending.is_empty()).then(|| resolver.pressed + CHORD_TIMEOUT)
+    })
+}
+
+/// Gives up waiting for the rest of a chord once `CHORD_TIMEOUT` has passed
+pub(crate) fn expire(now: Duration) -> Vec<Input> {
+    RESOLVER.with_borrow_mut(|resolver| {
+        if resolver.pending.is_empty() || now.saturating_sub(resolver.pressed) < CHORD_TIMEOUT {
+            return vec![];
+        }
+        let bindings = resolver.bindings();
+        resolver.flush(&bindings)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::{
+        message::prelude::*,
+        prelude::{Harness, Size, column, text, text_field},
+        render::dispatch,
+        widget::prelude::*,
+    };
+
+    fn key(s: &str) -> Key {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn keys_parse_and_normalize() {
+        key("Ctrl+Shift+Z").must_be(Key::new(
+            KeyCode::Char('z'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        ));
+        key("Ctrl+Shift+Z").must_be(Key::from(KeyEvent::new(
+            KeyCode::Char('Z'),
+            KeyModifiers::CONTROL,
+        )));
+        key("ctrl+Z").must_be(Key::from(KeyEvent::new(
+            KeyCode::Char('z'),
+            KeyModifiers::CONTROL,
+        )));
+        key("?").must_be(Key::from(KeyEvent::new(
+            KeyCode::Char('?'),
+            KeyModifiers::SHIFT,
+        )));
+        key("Ctrl++").must_be(Key::new(KeyCode::Char('+'), KeyModifiers::CONTROL));
+        key("F5").must_be(Key::new(KeyCode::F(5), KeyModifiers::NONE));
+        key("f").must_be(Key::new(KeyCode::Char('f'), KeyModifiers::NONE));
+        "Hyper+X".parse::<Key>().is_err().must_be(true);
+        "Ctrl+K Ctrl+S"
+            .parse::<KeySequence>()
+            .map(|sequence| sequence.0.len())
+            .ok()
+            .must_be(Some(2));
+    }
+
+    /// A widget binding `Ctrl+K Ctrl+S` and `g g` that records everything it receives
+    fn recorder() -> (Component, Rc<RefCell<Vec<String>>>) {
+        let log = Rc::new(RefCell::new(vec![]));
+        let widget = Widget::stateful(
+            log.clone(),
+            |this, msg| {
+                let mut log = this.state.borrow_mut();
+                switch(msg)
+                    .case(|&Action(action)| log.push(action.to_string()))
+                    .case(|event: &KeyEvent| log.push(format!("{:?}", event.code)));
+                Propagate
+            },
+            |_| text(""),
+        );
+        let keymap = Keymap::new()
+            .bind("save", "Ctrl+K Ctrl+S")
+            .bind("top", "g g");
+        (with_keymap(widget, keymap), log)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    #[test]
+    fn chords_dispatch_actions() {
+        let (widget, log) = recorder();
+        let mut harness = Harness::mount(widget, Size { x: 4, y: 1 });
+        harness.key(ctrl('k')).key(ctrl('s'));
+        harness.press(KeyCode::Char('g')).press(KeyCode::Char('g'));
+        log.borrow().clone().must_be(vec!["save", "top"]);
+    }
+
+    #[test]
+    fn broken_chords_are_dropped_and_single_keys_time_out() {
+        let (widget, log) = recorder();
+        let mut harness = Harness::mount(widget, Size { x: 4, y: 1 });
+        harness.key(ctrl('k')).press(KeyCode::Char('x'));
+        harness.press(KeyCode::Char('g'));
+        log.borrow().is_empty().must_be(true);
+        harness.tick(CHORD_TIMEOUT);
+        log.borrow().clone().must_be(vec!["Char('g')"]);
+    }
+
+    #[test]
+    fn apps_can_remap_actions() {
+        let (widget, log) = recorder();
+        let mut harness = Harness::mount(widget, Size { x: 4, y: 1 });
+        remap("save", ["Ctrl+S", "F2"]).is_ok().must_be(true);
+        harness.key(ctrl('k')).key(ctrl('s')).press(KeyCode::F(2));
+        log.borrow()
+            .clone()
+            .must_be(vec!["Char('k')", "save", "save"]);
+        remap("save", ["Hyper+S"]).is_err().must_be(true);
+    }
+
+    #[test]
+    fn only_the_focused_path_is_bound() {
+        let (widget, log) = recorder();
+        let (field, buffer) = text_field("");
+        let mut harness = Harness::mount(column([widget, field]), Size { x: 4, y: 2 });
+        harness.type_str("gg");
+        buffer.borrow().clone().must_be("gg");
+        log.borrow().is_empty().must_be(true);
+    }
+
+    #[test]
+    fn focused_widgets_cannot_take_over_quitting() {
+        let (widget, _) = recorder();
+        let _harness = Harness::mount(
+            with_keymap(widget, Keymap::new().bind("save", "Ctrl+C")),
+            Size { x: 4, y: 1 },
+        );
+        dispatch(resolve(ctrl('c'), Duration::ZERO)).must_be(true);
+        let (field, _) = text_field("");
+        let _harness = Harness::mount(field, Size { x: 4, y: 1 });
+        dispatch(resolve(ctrl('c'), Duration::ZERO)).must_be(true);
+    }
+}
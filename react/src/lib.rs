@@ -1,18 +1,35 @@
+pub mod animation;
+pub mod clipboard;
 pub mod component;
+pub mod context;
 pub mod displaylist;
 pub mod element;
 pub mod elements;
+pub mod focus;
 pub mod frame;
+pub mod hooks;
+pub mod keymap;
+pub mod lifecycle;
 pub mod message;
+pub mod mouse;
+pub mod reconcile;
 pub mod render;
 pub mod runtime;
+pub mod style;
+pub mod testing;
+pub mod tick;
+pub mod tree;
+pub mod unicode;
 pub mod widget;
 pub mod widgets;
 
 pub mod prelude {
     pub use super::{
-        component::prelude::*, displaylist::prelude::*, element::prelude::*, elements::prelude::*,
-        frame::prelude::*, message::prelude::*, render::prelude::*, runtime::prelude::*,
+        animation::prelude::*, clipboard::prelude::*, component::prelude::*, context::prelude::*,
+        displaylist::prelude::*, element::prelude::*, elements::prelude::*, focus::prelude::*,
+        frame::prelude::*, hooks::prelude::*, keymap::prelude::*, lifecycle::prelude::*,
+        message::prelude::*, mouse::prelude::*, reconcile::prelude::*, render::prelude::*,
+        runtime::prelude::*, style::prelude::*, testing::prelude::*, tick::prelude::*,
         widget::prelude::*, widgets::prelude::*,
     };
 }
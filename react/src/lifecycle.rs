@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+
+use crate::component::prelude::*;
+
+pub mod prelude {
+    pub use super::{on_mount, on_unmount};
+}
+
+/// Calls `f` whenever `component` is attached to the tree, before it is first built
+pub fn on_mount(component: Component, f: impl FnMut() + 'static) -> Component {
+    component.borrow_mut().add_mount_callback(Box::new(f));
+    component
+}
+
+/// Calls `f` whenever a rebuild discards `component`, or the app stops. A rebuild that
+/// reuses `component` instead swaps in the callbacks given to the fresh instance.
+pub fn on_unmount(component: Component, f: impl FnMut() + 'static) -> Component {
+    component.borrow_mut().add_unmount_callback(Box::new(f));
+    component
+}
+
+/// `component` and everything attached below it
+pub(crate) fn subtree(component: &Component) -> Vec<Component> {
+    let mut components = vec![];
+    collect(component, &mut components);
+    components
+}
+
+fn collect(component: &Component, into: &mut Vec<Component>) {
+    into.push(component.clone());
+    for child in component.borrow_mut().attached() {
+        collect(&child, into);
+    }
+}
+
+/// Unmounts whatever was in `old`, the subtree from before a rebuild, but is not part of the
+/// `new` one
+pub(crate) fn detach(old: Vec<Component>, new: &Component) {
+    let kept = subtree(new)
+        .iter()
+        .map(|component| component.borrow().id())
+        .collect::<HashSet<_>>();
+    for component in old {
+        let id = component.borrow().id();
+        if !kept.contains(&id) {
+            component.borrow_mut().on_unmount();
+        }
+    }
+}
+
+/// Unmounts the whole tree under `root`
+pub(crate) fn unmount(root: &Component) {
+    for component in subtree(root) {
+        component.borrow_mut().on_unmount();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::RefCell,
+        rc::Rc,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crossterm::event::{KeyCode, KeyEvent};
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::{
+        message::prelude::*,
+        prelude::{Harness, Size, column, keyed, text, use_effect},
+        runtime::go,
+        widget::Widget,
+    };
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    fn logged(log: &Log, name: &'static str, component: Component) -> Component {
+        let (mounted, unmounted) = (log.clone(), log.clone());
+        on_unmount(
+            on_mount(component, move || {
+                mounted.borrow_mut().push(format!("+{name}"))
+            }),
+            move || unmounted.borrow_mut().push(format!("-{name}")),
+        )
+    }
+
+    /// Rebuilds `child` on every key press until Esc is pressed
+    fn until_esc(child: impl Fn() -> Component + 'static) -> Component {
+        Widget::stateful(
+            true,
+            |this, msg| {
+                switch(msg).case(|event: &KeyEvent| {
+                    this.set_state(|shown| *shown = event.code != KeyCode::Esc)
+                });
+                Propagate
+            },
+            move |&shown| if shown { child() } else { text("") },
+        )
+    }
+
+    /// Holds on to `component`, so that only unmounting can stop what it started
+    fn held(holder: &Rc<RefCell<Vec<Component>>>, component: Component) -> Component {
+        holder.borrow_mut().push(component.clone());
+        component
+    }
+
+    /// Keeps a flag up until the task holding it is dropped
+    struct Alive(Arc<AtomicBool>);
+
+    impl Drop for Alive {
+        fn drop(&mut self) {
+            self.0.store(false, Ordering::SeqCst);
+        }
+    }
+
+    fn pending_forever(alive: Arc<AtomicBool>) -> impl Future<Output = ()> + Send + Sync {
+        alive.store(true, Ordering::SeqCst);
+        let alive = Alive(alive);
+        async move {
+            let _alive = alive;
+            std::future::pending::<()>().await
+        }
+    }
+
+    fn eventually_stops(alive: &AtomicBool) -> bool {
+        let start = Instant::now();
+        while alive.load(Ordering::SeqCst) {
+            if start.elapsed() > Duration::from_secs(1) {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        true
+    }
+
+    /// Waits for `count` to reach `expected`, then a little longer in case it goes past it
+    fn settles_at(count: &AtomicUsize, expected: usize) -> usize {
+        let start = Instant::now();
+        while count.load(Ordering::SeqCst) < expected && start.elapsed() < Duration::from_secs(1) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        thread::sleep(Duration::from_millis(20));
+        count.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn rebuilds_keep_reused_components_and_unmount_discarded_ones() {
+        let log = Log::default();
+        let tree = log.clone();
+        let mut harness = Harness::mount(
+            logged(
+                &log,
+                "root",
+                until_esc(move || {
+                    column([
+                        logged(&tree, "a", text("a")),
+                        keyed("b", logged(&tree, "b", text("b"))),
+                    ])
+                }),
+            ),
+            Size { x: 1, y: 2 },
+        );
+        log.borrow().clone().must_be(vec!["+root", "+a", "+b"]);
+        harness.press(KeyCode::Char('x'));
+        log.borrow().len().must_be(3);
+        harness.press(KeyCode::Esc);
+        log.borrow()[3..].to_vec().must_be(vec!["-a", "-b"]);
+        harness.unmount();
+        log.borrow()
+            .last()
+            .cloned()
+            .must_be(Some("-root".to_string()));
+    }
+
+    #[test]
+    fn reused_components_unmount_with_their_latest_callbacks() {
+        let log = Log::default();
+        let (builds, tree) = (Rc::new(RefCell::new(0)), log.clone());
+        let mut harness = Harness::mount(
+            until_esc(move || {
+                *builds.borrow_mut() += 1;
+                let (build, log) = (*builds.borrow(), tree.clone());
+                on_unmount(text("a"), move || {
+                    log.borrow_mut().push(format!("-a{build}"))
+                })
+            }),
+            Size { x: 1, y: 1 },
+        );
+        harness.press(KeyCode::Char('x'));
+        harness.unmount();
+        log.borrow().clone().must_be(vec!["-a2"]);
+    }
+
+    #[test]
+    fn tasks_started_on_mount_are_aborted_on_unmount() {
+        let alive = Arc::new(AtomicBool::new(false));
+        let task = alive.clone();
+        let holder = Rc::default();
+        let hold = Rc::clone(&holder);
+        let mut harness = Harness::mount(
+            until_esc(move || {
+                let task = task.clone();
+                held(
+                    &hold,
+                    Widget::function(move || {
+                        let task = task.clone();
+                        use_effect((), move || {
+                            let handle = go(pending_forever(task));
+                            move || handle.abort()
+                        });
+                        text("busy")
+                    }),
+                )
+            }),
+            Size { x: 4, y: 1 },
+        );
+        alive.load(Ordering::SeqCst).must_be(true);
+        harness.press(KeyCode::Esc);
+        eventually_stops(&alive).must_be(true);
+        holder.borrow().len().must_be(1);
+    }
+
+    #[test]
+    fn callbacks_can_stop_what_they_started() {
+        let alive = Arc::new(AtomicBool::new(false));
+        let task = alive.clone();
+        let mut harness = Harness::mount(
+            until_esc(move || {
+                let running = Rc::new(RefCell::new(None));
+                let (started, stopped) = (running.clone(), running.clone());
+                let task = task.clone();
+                on_unmount(
+                    on_mount(column([text("busy")]), move || {
+                        let handle = go(pending_forever(task.clone()));
+                        started.replace(Some(handle.abort_handle()));
+                    }),
+                    move || {
+                        if let Some(handle) = stopped.take() {
+                            handle.abort();
+                        }
+                    },
+                )
+            }),
+            Size { x: 4, y: 1 },
+        );
+        alive.load(Ordering::SeqCst).must_be(true);
+        harness.press(KeyCode::Esc);
+        eventually_stops(&alive).must_be(true);
+    }
+
+    #[test]
+    fn future_widgets_start_once_unless_their_key_changes() {
+        let starts = Arc::new(AtomicUsize::new(0));
+        let counted = starts.clone();
+        let mut harness = Harness::mount(
+            Widget::stateful(
+                0,
+                |this, msg| {
+                    switch(msg).case(|_: &KeyEvent| this.set_state(|presses| *presses += 1));
+                    Propagate
+                },
+                move |&presses| {
+                    let counted = counted.clone();
+                    keyed(
+                        presses / 2,
+                        Widget::future(
+                            async move {
+                                counted.fetch_add(1, Ordering::SeqCst);
+                            },
+                            |_, _| Propagate,
+                            |_| text(""),
+                        ),
+                    )
+                },
+            ),
+            Size { x: 1, y: 1 },
+        );
+        settles_at(&starts, 1).must_be(1);
+        harness.press(KeyCode::Char('x'));
+        settles_at(&starts, 1).must_be(1);
+        harness.press(KeyCode::Char('x'));
+        settles_at(&starts, 2).must_be(2);
+    }
+
+    #[test]
+    fn future_widgets_abort_their_task_on_unmount() {
+        let alive = Arc::new(AtomicBool::new(false));
+        let task = alive.clone();
+        let holder = Rc::default();
+        let hold = Rc::clone(&holder);
+        let mut harness = Harness::mount(
+            until_esc(move || {
+                held(
+                    &hold,
+                    Widget::future(
+                        pending_forever(task.clone()),
+                        |_, _| Propagate,
+                        |_| text(""),
+                    ),
+                )
+            }),
+            Size { x: 1, y: 1 },
+        );
+        alive.load(Ordering::SeqCst).must_be(true);
+        harness.press(KeyCode::Esc);
+        eventually_stops(&alive).must_be(true);
+        holder.borrow().len().must_be(1);
+    }
+}
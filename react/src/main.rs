@@ -9,7 +9,13 @@ fn main() -> Result<()> {
     //     column([counter(12), text_field("").0]),
     //     column([text_field("").0, download("https://www.rust-lang.org")]),
     // ]))
-    render(todo_list())
+    render_with(
+        todo_list(),
+        RenderOptions {
+            mouse: true,
+            osc52: true,
+        },
+    )
 }
 
 struct AddTask(String);
@@ -24,36 +30,37 @@ fn todo_list() -> Component {
                     this.set_state(|tasks| tasks.push(task.clone()));
                     Intercept
                 })
+                .case(|&Activated { index, .. }| {
+                    this.set_state(|tasks| _ = tasks.remove(index));
+                    Intercept
+                })
                 .default(|| Propagate)
         },
         |tasks| {
-            column(
-                tasks
-                    .iter()
-                    .map(|task| text(task.clone()))
-                    .chain([add_task()]),
-            )
+            column([
+                flex(
+                    1,
+                    bordered_with(
+                        Border::default().title("Tasks"),
+                        scroll_view(select_list(tasks)),
+                    ),
+                ),
+                fixed(1, add_task()),
+            ])
         },
     )
 }
 
 fn add_task() -> Component {
-    let (textfield, buffer) = text_field("");
-    Widget::stateful(
-        (textfield, buffer),
-        |this, msg| {
-            switch(msg)
-                .case(|event: &KeyEvent| match event.code {
-                    KeyCode::Enter => {
-                        eprintln!("Pressed enter!");
-                        let (_, buffer) = &this.state;
-                        send(AddTask(buffer.borrow().clone()));
-                        Intercept
-                    }
-                    _ => Propagate,
-                })
-                .default(|| Propagate)
-        },
-        |(textfield, _)| textfield.clone(),
-    )
+    Widget::function(|| {
+        let (textfield, buffer) = use_memo((), |_| text_field(""));
+        use_message(move |event: &KeyEvent| match event.code {
+            KeyCode::Enter => {
+                send(AddTask(buffer.borrow().clone()));
+                Intercept
+            }
+            _ => Propagate,
+        });
+        textfield
+    })
 }
@@ -1,10 +1,22 @@
-use std::{cell::RefCell, collections::VecDeque};
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::VecDeque,
+    sync::mpsc::{Receiver, Sender, channel},
+};
 
 use stdext::prelude::{Anything, any};
 
+use crate::{
+    component::prelude::*,
+    runtime::{Wake, waker},
+    tree,
+};
+
 pub mod prelude {
     pub use super::{
-        Message, MessageFlow, MessageFlow::Intercept, MessageFlow::Propagate, handle_messages, send,
+        Delivery, Handle, Message, MessageFlow, MessageFlow::Intercept, MessageFlow::Propagate,
+        bubble, handle_messages, send, send_to,
     };
 }
 
@@ -22,20 +34,191 @@ impl Default for MessageFlow {
     }
 }
 
+/// Who a message is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Delivery {
+    /// Every widget, from the root down until one intercepts it
+    Broadcast,
+    /// Only the widget with this id
+    To(usize),
+    /// The widget with this id, then its ancestors up to the root until one intercepts it
+    Bubble(usize),
+}
+
+type Remote = (Delivery, Box<dyn Any + Send>);
+
 thread_local! {
-    pub(crate) static MESSAGE_QUEUE: RefCell<VecDeque<Message<'static>>> = RefCell::new(VecDeque::new());
+    pub(crate) static MESSAGE_QUEUE: RefCell<VecDeque<(Delivery, Message<'static>)>> = RefCell::new(VecDeque::new());
+    /// Messages sent through a `Handle`, possibly from other threads
+    static REMOTE: (Sender<Remote>, Receiver<Remote>) = channel();
+}
+
+fn enqueue(delivery: Delivery, message: Message<'static>) {
+    MESSAGE_QUEUE.with_borrow_mut(|queue| queue.push_back((delivery, message)));
 }
 
 pub fn send<T: 'static>(message: T) {
-    MESSAGE_QUEUE.with_borrow_mut(|queue| queue.push_back(any(message)));
+    enqueue(Delivery::Broadcast, any(message));
+}
+
+/// Sends `message` to the widget `id` alone
+pub fn send_to<T: 'static>(id: usize, message: T) {
+    enqueue(Delivery::To(id), any(message));
+}
+
+/// Sends `message` to the widget `id` and then up through its ancestors
+pub fn bubble<T: 'static>(id: usize, message: T) {
+    enqueue(Delivery::Bubble(id), any(message));
+}
+
+/// Sends messages to one widget from any thread. They are delivered with the next update.
+#[derive(Debug, Clone)]
+pub struct Handle {
+    id: usize,
+    sender: Sender<Remote>,
+    waker: Sender<Wake>,
 }
 
-pub fn handle_messages(mut f: impl FnMut(&Message)) {
+impl Handle {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    pub fn send<T: Send + 'static>(&self, message: T) {
+        _ = self.sender.send((Delivery::To(self.id), Box::new(message)));
+        _ = self.waker.send(Wake::Poll);
+    }
+    pub fn bubble<T: Send + 'static>(&self, message: T) {
+        _ = self
+            .sender
+            .send((Delivery::Bubble(self.id), Box::new(message)));
+        _ = self.waker.send(Wake::Poll);
+    }
+}
+
+pub(crate) fn handle(id: usize) -> Handle {
+    Handle {
+        id,
+        sender: REMOTE.with(|(sender, _)| sender.clone()),
+        waker: waker(),
+    }
+}
+
+pub fn handle_messages(mut f: impl FnMut(Delivery, &Message)) {
+    REMOTE.with(|(_, receiver)| {
+        for (delivery, message) in receiver.try_iter() {
+            enqueue(delivery, message);
+        }
+    });
     let mut msgs = vec![];
     MESSAGE_QUEUE.with_borrow_mut(|queue| {
         while let Some(msg) = queue.pop_front() {
             msgs.push(msg);
         }
     });
-    msgs.into_iter().for_each(|msg| f(&msg));
+    msgs.into_iter()
+        .for_each(|(delivery, msg)| f(delivery, &msg));
+}
+
+pub(crate) fn has_pending_messages() -> bool {
+    MESSAGE_QUEUE.with_borrow(|queue| !queue.is_empty())
+}
+
+/// Walks from `root` down to `target` to deliver `msg`, bubbling back up if asked to.
+/// Messages for widgets that are no longer mounted are dropped.
+pub(crate) fn deliver(root: &Component, target: usize, msg: &Message, bubble: bool) {
+    let mut path = tree::ancestors(target);
+    path.reverse();
+    if path.first() != Some(&root.borrow().id()) {
+        return;
+    }
+    root.borrow_mut().deliver(&path[1..], msg, bubble);
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc, thread};
+
+    use stdext::prelude::*;
+
+    use super::{prelude::*, *};
+    use crate::{
+        prelude::{Harness, Size, column, text},
+        widget::prelude::*,
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Ping(&'static str);
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// A widget named `name` that logs the pings it gets and lets them through unless `intercept`
+    fn node(name: &'static str, log: &Log, intercept: bool, children: Vec<Component>) -> Component {
+        let log = log.clone();
+        Widget::stateful(
+            (),
+            move |_, msg| {
+                switch(msg)
+                    .case(|&Ping(ping)| {
+                        log.borrow_mut().push(format!("{name}:{ping}"));
+                        if intercept { Intercept } else { Propagate }
+                    })
+                    .default(|| Propagate)
+            },
+            move |_| match children.as_slice() {
+                [] => text(name),
+                children => column(children.iter().cloned()),
+            },
+        )
+    }
+
+    fn tree(log: &Log, intercept: bool) -> (Harness, [usize; 3]) {
+        let leaf = node("leaf", log, false, vec![]);
+        let sibling = node("sibling", log, false, vec![]);
+        let parent = node("parent", log, intercept, vec![leaf.clone(), sibling]);
+        let root = node("root", log, false, vec![parent.clone()]);
+        let ids = [root.borrow().id(), parent.borrow().id(), leaf.borrow().id()];
+        (Harness::mount(root, Size { x: 8, y: 2 }), ids)
+    }
+
+    #[test]
+    fn send_to_reaches_only_the_target() {
+        let log = Log::default();
+        let (mut harness, [_, parent, leaf]) = tree(&log, false);
+        send_to(parent, Ping("a"));
+        send_to(leaf, Ping("b"));
+        harness.tick(Default::default());
+        log.borrow().clone().must_be(vec!["parent:a", "leaf:b"]);
+    }
+
+    #[test]
+    fn bubbling_walks_up_until_intercepted() {
+        let log = Log::default();
+        let (mut harness, [_, _, leaf]) = tree(&log, false);
+        bubble(leaf, Ping("up"));
+        harness.tick(Default::default());
+        log.borrow()
+            .clone()
+            .must_be(vec!["leaf:up", "parent:up", "root:up"]);
+
+        let log = Log::default();
+        let (mut harness, [_, _, leaf]) = tree(&log, true);
+        bubble(leaf, Ping("up"));
+        harness.tick(Default::default());
+        log.borrow().clone().must_be(vec!["leaf:up", "parent:up"]);
+    }
+
+    #[test]
+    fn handles_deliver_from_other_threads() {
+        let log = Log::default();
+        let (mut harness, [root, _, leaf]) = tree(&log, false);
+        let handle = handle(leaf);
+        thread::spawn(move || handle.bubble(Ping("async")))
+            .join()
+            .unwrap();
+        send_to(root + 100, Ping("nobody"));
+        harness.tick(Default::default());
+        log.borrow()
+            .clone()
+            .must_be(vec!["leaf:async", "parent:async", "root:async"]);
+    }
 }
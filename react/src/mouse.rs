@@ -0,0 +1,171 @@
+use std::cell::RefCell;
+
+use crossterm::event::{KeyModifiers, MouseEvent, MouseEventKind};
+
+use crate::{
+    focus,
+    message::send,
+    prelude::{Point, Rect, Regions},
+    tree,
+};
+
+pub mod prelude {
+    pub use super::Mouse;
+}
+
+/// A mouse event, delivered along the path from the root to the innermost widget under the
+/// pointer. `position` is relative to the origin of the widget receiving it.
+#[derive(Debug, Clone, Copy)]
+pub struct Mouse {
+    pub kind: MouseEventKind,
+    pub position: Point,
+    pub absolute: Point,
+    pub modifiers: KeyModifiers,
+    pub(crate) target: usize,
+}
+
+impl Mouse {
+    /// This event as seen by the widget `id`, or `None` if `id` is not on its path
+    pub(crate) fn relative_to(&self, id: usize) -> Option<Mouse> {
+        if !tree::is_ancestor(id, self.target) {
+            return None;
+        }
+        let origin = region(id).map(|rect| rect.origin).unwrap_or_default();
+        Some(Mouse {
+            position: self.absolute - origin,
+            ..*self
+        })
+    }
+}
+
+thread_local! {
+    static REGIONS: RefCell<Regions> = RefCell::new(Regions::default());
+    /// The widget a mouse button went down on followed by its ancestors. The innermost of them
+    /// still mounted keeps receiving the drag until release, even if the others were rebuilt.
+    static CAPTURE: RefCell<Vec<usize>> = const { RefCell::new(vec![]) };
+}
+
+/// Remembers where widgets were drawn in the last frame, for hit-testing
+pub(crate) fn set_regions(regions: Regions) {
+    REGIONS.set(regions);
+}
+
+/// Where the widget `id` was drawn in the last frame
+pub fn region(id: usize) -> Option<Rect> {
+    REGIONS.with_borrow(|regions| regions.get(id))
+}
+
+/// Sends `event` as a `Mouse` message to the widget under the pointer.
+/// Pressing a button also focuses the nearest focusable widget there.
+pub(crate) fn route(event: MouseEvent) {
+    let absolute = Point {
+        x: event.column as isize,
+        y: event.row as isize,
+    };
+    let hit = REGIONS.with_borrow(|regions| regions.hit(absolute));
+    let captured =
+        || CAPTURE.with_borrow(|capture| capture.iter().copied().find(|&id| tree::is_mounted(id)));
+    let target = match event.kind {
+        MouseEventKind::Down(_) => {
+            CAPTURE.set(hit.map(tree::ancestors).unwrap_or_default());
+            if let Some(hit) = hit {
+                focus::focus_nearest(hit);
+            }
+            hit
+        }
+        MouseEventKind::Drag(_) => captured().or(hit),
+        MouseEventKind::Up(_) => {
+            let captured = captured();
+            CAPTURE.take();
+            captured.or(hit)
+        }
+        _ => hit,
+    };
+    if let Some(target) = target {
+        send(Mouse {
+            kind: event.kind,
+            position: absolute,
+            absolute,
+            modifiers: event.modifiers,
+            target,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+
+    use crossterm::event::{KeyCode, MouseButton};
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::{
+        message::MessageFlow::Propagate,
+        prelude::{Component, Harness, Size, column, fixed, text, text_field},
+        widget::prelude::*,
+    };
+
+    fn probe(log: Rc<RefCell<Vec<Point>>>) -> Component {
+        Widget::stateful(
+            (),
+            move |_, msg| {
+                switch(msg).case(|event: &Mouse| log.borrow_mut().push(event.position));
+                Propagate
+            },
+            |_| text("probe"),
+        )
+    }
+
+    #[test]
+    fn positions_are_relative_to_the_receiver() {
+        let top = Rc::new(RefCell::new(vec![]));
+        let bottom = Rc::new(RefCell::new(vec![]));
+        let mut harness = Harness::mount(
+            column([
+                fixed(2, probe(top.clone())),
+                fixed(2, probe(bottom.clone())),
+            ]),
+            Size { x: 10, y: 4 },
+        );
+        harness.click(Point { x: 3, y: 3 });
+        top.borrow().is_empty().must_be(true);
+        bottom
+            .borrow()
+            .clone()
+            .must_be(vec![Point { x: 3, y: 1 }, Point { x: 3, y: 1 }]);
+    }
+
+    #[test]
+    fn clicking_focuses_a_field() {
+        let mut harness = Harness::mount(
+            column([text_field("").0, text_field("").0]),
+            Size { x: 10, y: 2 },
+        );
+        harness.click(Point { x: 0, y: 1 }).type_str("b");
+        harness.lines().must_be(vec!["", "b"]);
+        harness.press(KeyCode::Tab).type_str("a");
+        harness.lines().must_be(vec!["a", "b"]);
+    }
+
+    #[test]
+    fn drags_stay_with_the_pressed_widget() {
+        let top = Rc::new(RefCell::new(vec![]));
+        let bottom = Rc::new(RefCell::new(vec![]));
+        let mut harness = Harness::mount(
+            column([
+                fixed(1, probe(top.clone())),
+                fixed(1, probe(bottom.clone())),
+            ]),
+            Size { x: 10, y: 2 },
+        );
+        let left = MouseButton::Left;
+        harness
+            .mouse(MouseEventKind::Down(left), Point { x: 1, y: 0 })
+            .mouse(MouseEventKind::Drag(left), Point { x: 1, y: 1 })
+            .mouse(MouseEventKind::Up(left), Point { x: 1, y: 1 });
+        top.borrow().len().must_be(3);
+        top.borrow()[2].must_be(Point { x: 1, y: 1 });
+        bottom.borrow().is_empty().must_be(true);
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    collections::HashSet,
+    hash::{DefaultHasher, Hash, Hasher},
+    rc::Rc,
+};
+
+use crate::{component::prelude::*, widget::Widget};
+
+pub mod prelude {
+    pub use super::{keyed, on_reuse};
+}
+
+/// Marks `component` with a key that stays the same across rebuilds of its parent.
+/// A rebuild then reuses the previous instance with that key, and with it its state,
+/// wherever it moved among its siblings.
+pub fn keyed(key: impl Hash, component: Component) -> Component {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    component.borrow_mut().set_key(hasher.finish());
+    component
+}
+
+/// Has a rebuild that reuses the previous instance of `component` bring the state of the fresh
+/// one over with `merge(kept, fresh)`. Otherwise stateful widgets keep their own state and
+/// elemental ones take the fresh state.
+pub fn on_reuse<State: 'static>(
+    component: Component,
+    merge: fn(&mut State, &mut State),
+) -> Component {
+    component
+        .borrow_mut()
+        .as_any()
+        .downcast_mut::<Widget<State>>()
+        .expect("`on_reuse` needs a widget with this state")
+        .set_merge(merge);
+    component
+}
+
+/// Matches the freshly built tree `new` against the previous one, `old`.
+/// When both are of the same kind and have the same key, the previous instance is kept and
+/// adopts the props of the new one. The children of containers are matched by key, or by
+/// position among the unkeyed ones.
+pub(crate) fn reconcile(old: &Component, new: Component) -> Component {
+    if Rc::ptr_eq(old, &new) {
+        return new;
+    }
+    let (old_kind, old_key) = {
+        let old = old.borrow();
+        (old.kind(), old.key())
+    };
+    let (new_kind, new_key) = {
+        let new = new.borrow();
+        (new.kind(), new.key())
+    };
+    if old_kind != new_kind || old_key != new_key {
+        return new;
+    }
+    let old_children = old
+        .borrow_mut()
+        .children()
+        .into_iter()
+        .map(|child| child.clone())
+        .collect::<Vec<_>>();
+    let mut unkeyed = old_children
+        .iter()
+        .filter(|old| old.borrow().key().is_none());
+    let mut keys = HashSet::new();
+    for child in new.borrow_mut().children() {
+        let key = child.borrow().key();
+        // A key seen twice would put one instance in two places, so only the first one reuses
+        let unique = key.is_none_or(|key| keys.insert(key));
+        debug_assert!(unique, "Siblings must not share a key");
+        let previous = match key {
+            Some(key) if unique => old_children
+                .iter()
+                .find(|old| old.borrow().key() == Some(key)),
+            Some(_) => None,
+            None => unkeyed.next(),
+        };
+        if let Some(previous) = previous {
+            *child = reconcile(previous, child.clone());
+        }
+    }
+    old.borrow_mut().adopt(&mut *new.borrow_mut());
+    old.clone()
+}
+
+#[cfg(test)]
+mod test {
+    use crossterm::event::KeyCode;
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::{
+        message::prelude::*,
+        prelude::{Harness, Size, column, counter, text, text_field},
+    };
+
+    struct Prepend(&'static str);
+
+    /// A column of counters, one per name, that can grow at the top
+    fn counters(key: bool) -> Component {
+        Widget::stateful(
+            vec!["b"],
+            |this, msg| {
+                switch(msg).case(|&Prepend(name)| this.set_state(|names| names.insert(0, name)));
+                Propagate
+            },
+            move |names| {
+                column(names.iter().map(|&name| match key {
+                    true => keyed(name, counter(0)),
+                    false => counter(0),
+                }))
+            },
+        )
+    }
+
+    #[test]
+    fn keyed_children_keep_their_state() {
+        let mut harness = Harness::mount(counters(true), Size { x: 3, y: 2 });
+        harness.press(KeyCode::Char('+'));
+        harness.lines().must_be(vec!["1", ""]);
+        harness.send(Prepend("a"));
+        harness.lines().must_be(vec!["0", "1"]);
+    }
+
+    #[test]
+    fn unkeyed_children_are_matched_by_position() {
+        let mut harness = Harness::mount(counters(false), Size { x: 3, y: 2 });
+        harness.press(KeyCode::Char('+'));
+        harness.send(Prepend("a"));
+        harness.lines().must_be(vec!["1", "0"]);
+    }
+
+    #[test]
+    fn reused_children_show_their_new_props() {
+        let root = Widget::stateful(
+            0,
+            |this, msg| {
+                switch(msg).case(|_: &Prepend| this.set_state(|n| *n += 1));
+                Propagate
+            },
+            |n| column([keyed("k", text(n.to_string())), text(n.to_string())]),
+        );
+        let mut harness = Harness::mount(root, Size { x: 1, y: 2 });
+        harness.send(Prepend(""));
+        harness.lines().must_be(vec!["1", "1"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Siblings must not share a key")]
+    fn duplicate_keys_are_rejected() {
+        let mut harness = Harness::mount(counters(true), Size { x: 3, y: 2 });
+        harness.send(Prepend("b"));
+    }
+
+    #[test]
+    fn reused_fields_keep_their_buffer_and_focus() {
+        let root = Widget::stateful(
+            vec![],
+            |this, msg| {
+                switch(msg).case(|&Prepend(line)| this.set_state(|lines| lines.push(line)));
+                Propagate
+            },
+            |lines| {
+                column(
+                    lines
+                        .iter()
+                        .map(|&line| text(line))
+                        .chain([keyed("input", text_field("").0)]),
+                )
+            },
+        );
+        let mut harness = Harness::mount(root, Size { x: 5, y: 2 });
+        harness.type_str("ab").send(Prepend("x")).type_str("c");
+        harness.lines().must_be(vec!["x", "abc"]);
+    }
+}
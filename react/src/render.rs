@@ -1,8 +1,17 @@
 use crate::{
+    clipboard::{self, Paste},
     component::prelude::*,
+    focus,
     frame::{Token, TokensExt},
-    message::{handle_messages, send},
-    prelude::{DisplayList, Element, Frame, FrameExt, Size},
+    hooks,
+    keymap::{self, Input, QUIT},
+    lifecycle,
+    message::{self, Delivery, handle_messages, has_pending_messages, send},
+    mouse,
+    prelude::{DisplayList, Element, Frame, FrameExt, Regions, Size},
+    runtime::{self, Poll, Wake, go, waker},
+    style::Style,
+    tick, tree,
 };
 use std::{
     io::{self, Write},
@@ -13,58 +22,193 @@ use std::{
 use crossterm::{
     ExecutableCommand, QueueableCommand,
     cursor::{Hide, MoveTo, Show},
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    event::{
+        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, EventStream,
+    },
     terminal::{
         Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
         enable_raw_mode,
     },
 };
+use futures::StreamExt;
 use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
 
 pub mod prelude {
-    pub use super::{Tick, render};
+    pub use super::{RenderOptions, Resize, render, render_with};
 }
 
-pub struct Tick(pub Duration);
+/// Broadcast once at startup and whenever the terminal is resized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resize(pub Size);
+
+enum RenderCommand {
+    /// Prints a frame, only touching the cells that changed since the last one
+    Print(Frame),
+    /// Forgets the last frame, as terminals reflow or clear on resize
+    Resize,
+    /// Writes an escape sequence between frames
+    Write(String),
+}
 
-fn print_frame(frame: Frame) -> std::io::Result<()> {
-    let mut stdout = io::stdout();
-    stdout.queue(Clear(ClearType::All))?;
-    for row_index in 0..frame.height() {
-        if row_index >= u16::MAX as usize {
-            break;
+/// Prints `frame`, only touching the cells that differ from `prev`.
+/// Falls back to a full repaint when there is no previous frame or the size changed.
+fn print_frame(prev: Option<&Frame>, frame: &Frame, out: &mut impl Write) -> std::io::Result<()> {
+    let same_size = prev.is_some_and(|prev| {
+        prev.height() == frame.height() && prev.iter().zip(frame).all(|(a, b)| a.len() == b.len())
+    });
+    let mut style = Style::default();
+    let mut s = String::new();
+    match prev {
+        Some(prev) if same_size => {
+            let mut cursor = None;
+            for (row_index, (old, new)) in
+                prev.iter().zip(frame).enumerate().take(u16::MAX as usize)
+            {
+                let mut col = 0;
+                while col < new.len() {
+                    if old[col] == new[col] {
+                        col += 1;
+                        continue;
+                    }
+                    let start = col;
+                    while col < new.len()
+                        && (old[col] != new[col] || new[col] == Token::Continuation)
+                    {
+                        col += 1;
+                    }
+                    if cursor != Some((start, row_index)) {
+                        out.queue(MoveTo(start as u16, row_index as u16))?;
+                    }
+                    new[start..col].write_styled(&mut style, &mut s);
+                    write!(out, "{s}")?;
+                    s.clear();
+                    cursor = Some((col, row_index));
+                }
+            }
+        }
+        _ => {
+            out.queue(Clear(ClearType::All))?;
+            for (row_index, row) in frame.iter().enumerate().take(u16::MAX as usize) {
+                out.queue(MoveTo(0, row_index as u16))?;
+                row.write_styled(&mut style, &mut s);
+                write!(out, "{s}")?;
+                s.clear();
+            }
         }
-        stdout.queue(MoveTo(0, row_index as u16))?;
-        print!("{}", frame[row_index].to_string());
     }
-    stdout.flush()?;
+    write!(out, "{}", style.transition(&Style::default()))?;
+    out.flush()?;
     Ok(())
 }
 
-fn setup() -> (
-    UnboundedSender<Box<dyn Element>>,
+/// Draws `element` onto a blank frame of the given size
+pub(crate) fn rasterize(element: &dyn Element, size: Size) -> (Frame, Regions) {
+    let mut display_list = DisplayList::default();
+    element.draw(size, &mut display_list);
+    let mut frame = vec![vec![Token::default(); size.x.max(0) as usize]; size.y.max(0) as usize];
+    let regions = display_list.draw_on(&mut frame);
+    (frame, regions)
+}
+
+/// Draws `element` onto a blank frame and publishes where every widget ended up, for
+/// hit-testing and scrolling into view
+pub(crate) fn draw(element: &dyn Element, size: Size) -> Frame {
+    let (frame, regions) = rasterize(element, size);
+    mouse::set_regions(regions);
+    frame
+}
+
+/// Messages sent while handling messages are delivered in follow-up rounds of the same update,
+/// up to this many rounds, so that e.g. focus changes show up in the same frame. The render loop
+/// picks up whatever is left right away, without waiting for input.
+const MAX_ROUNDS: usize = 4;
+
+/// Whether messages are waiting, or effects changed state after the last build
+fn unsettled() -> bool {
+    has_pending_messages() || hooks::changed()
+}
+
+/// Delivers every queued message to `widget`, then rebuilds whatever changed
+pub(crate) fn update(widget: &Component) -> (bool, Box<dyn Element>) {
+    let mut did_rebuild = false;
+    let mut round = 0;
+    loop {
+        handle_messages(|delivery, msg| match delivery {
+            Delivery::Broadcast => {
+                if !focus::traverse(msg) {
+                    widget.borrow_mut().on_message(msg)
+                }
+            }
+            Delivery::To(id) => message::deliver(widget, id, msg, false),
+            Delivery::Bubble(id) => message::deliver(widget, id, msg, true),
+        });
+        tree::begin();
+        focus::begin();
+        keymap::begin();
+        tick::begin();
+        hooks::begin();
+        let (rebuilt, element) = widget.borrow_mut().create_element();
+        did_rebuild |= rebuilt;
+        focus::settle();
+        round += 1;
+        if !unsettled() || round >= MAX_ROUNDS {
+            return (did_rebuild, element);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    /// Capture the mouse and deliver `Mouse` messages. Disables the terminal's own text selection.
+    pub mouse: bool,
+    /// Also put copied text into the host terminal's clipboard through OSC 52
+    pub osc52: bool,
+}
+
+fn terminal_size() -> std::io::Result<Size> {
+    let (cols, rows) = crossterm::terminal::size()?;
+    Ok(Size {
+        x: cols as isize,
+        y: rows as isize,
+    })
+}
+
+fn setup(
+    options: &RenderOptions,
+) -> (
+    UnboundedSender<RenderCommand>,
     std::thread::JoinHandle<std::io::Result<()>>,
 ) {
-    let (sender, mut receiver) = unbounded_channel::<Box<dyn Element>>();
+    let (sender, mut receiver) = unbounded_channel::<RenderCommand>();
+    let mouse = options.mouse;
     let rendering_task = thread::spawn(move || -> std::io::Result<()> {
         let mut stdout = io::stdout();
         enable_raw_mode()?;
         stdout.execute(EnterAlternateScreen)?;
         stdout.execute(Hide)?;
-        while let Some(element) = receiver.blocking_recv() {
-            let (cols, rows) = crossterm::terminal::size()?;
-            let mut display_list = DisplayList::default();
-            element.draw(
-                Size {
-                    x: cols as isize,
-                    y: rows as isize,
-                },
-                &mut display_list,
-            );
-            let mut frame = vec![vec![Token::Char(' '); cols as usize]; rows as usize];
-            display_list.draw_on(&mut frame);
-            print_frame(frame)?;
+        stdout.execute(EnableBracketedPaste)?;
+        if mouse {
+            stdout.execute(EnableMouseCapture)?;
+        }
+        let mut prev = None;
+        while let Some(command) = receiver.blocking_recv() {
+            match command {
+                RenderCommand::Print(frame) => {
+                    print_frame(prev.as_ref(), &frame, &mut stdout)?;
+                    prev = Some(frame);
+                }
+                RenderCommand::Resize => prev = None,
+                RenderCommand::Write(s) => {
+                    stdout.write_all(s.as_bytes())?;
+                    stdout.flush()?;
+                }
+            }
         }
+        if mouse {
+            stdout.execute(DisableMouseCapture)?;
+        }
+        stdout.execute(DisableBracketedPaste)?;
         stdout.execute(Show)?;
         stdout.execute(LeaveAlternateScreen)?;
         disable_raw_mode()?;
@@ -73,39 +217,208 @@ fn setup() -> (
     (sender, rendering_task)
 }
 
+/// Sends resolved key presses as messages, returning whether `QUIT` was among them
+pub(crate) fn dispatch(inputs: Vec<Input>) -> bool {
+    let mut quit = false;
+    for input in inputs {
+        match input {
+            Input::Key(event) => send(event),
+            Input::Action(action) if action.0 == QUIT => quit = true,
+            Input::Action(action) => send(action),
+        }
+    }
+    quit
+}
+
 pub fn render(widget: Component) -> std::io::Result<()> {
-    let (frame_sender, rendering_task) = setup();
+    render_with(widget, RenderOptions::default())
+}
+
+pub fn render_with(widget: Component, options: RenderOptions) -> std::io::Result<()> {
+    let (frame_sender, rendering_task) = setup(&options);
+    clipboard::set_export(options.osc52);
     let start = Instant::now();
-    let (_, element) = widget.borrow_mut().create_element();
-    _ = frame_sender.send(element);
+    let mut size = terminal_size()?;
+    send(Resize(size));
+    let (_, element) = update(&widget);
+    _ = frame_sender.send(RenderCommand::Print(draw(element.as_ref(), size)));
+    let events = waker();
+    go(async move {
+        let mut stream = EventStream::new();
+        while let Some(event) = stream.next().await {
+            if events.send(Wake::Event(event)).is_err() {
+                break;
+            }
+        }
+    });
     loop {
-        let tick_start = Instant::now();
-        if event::poll(Duration::default())? {
-            let event = event::read()?;
-            if let Event::Key(
-                event @ KeyEvent {
-                    code, modifiers, ..
-                },
-            ) = event
-            {
-                match (modifiers, code) {
-                    (KeyModifiers::CONTROL, KeyCode::Char('c')) => {
-                        drop(frame_sender);
-                        rendering_task
-                            .join()
-                            .expect("Failed to join printing task")?;
-                        return Ok(());
+        // Sleeps until something happens, or until the next tick or chord timeout is due.
+        // What the last update left over is taken care of right away.
+        let timeout = if unsettled() {
+            Some(Duration::ZERO)
+        } else {
+            [tick::next_due(), keymap::deadline()]
+                .into_iter()
+                .flatten()
+                .min()
+                .map(|due| due.saturating_sub(start.elapsed()))
+        };
+        let mut inputs = vec![];
+        let mut resized = false;
+        for wake in runtime::wait(timeout) {
+            match wake {
+                Wake::Event(event) => match event? {
+                    Event::Key(event) => inputs.extend(keymap::resolve(event, start.elapsed())),
+                    Event::Resize(cols, rows) => {
+                        size = Size {
+                            x: cols as isize,
+                            y: rows as isize,
+                        };
+                        resized = true;
+                        _ = frame_sender.send(RenderCommand::Resize);
+                        send(Resize(size));
                     }
-                    _ => send(event),
-                }
+                    Event::Mouse(event) => mouse::route(event),
+                    Event::Paste(s) => send(Paste(s)),
+                    _ => {}
+                },
+                Wake::Poll => send(Poll),
             }
         }
-        send(Tick(start.elapsed()));
-        handle_messages(|msg| widget.borrow_mut().on_message(msg));
-        let (did_rebuild, element) = widget.borrow_mut().create_element();
-        if did_rebuild {
-            _ = frame_sender.send(element);
+        inputs.extend(keymap::expire(start.elapsed()));
+        if dispatch(inputs) {
+            lifecycle::unmount(&widget);
+            drop(frame_sender);
+            rendering_task
+                .join()
+                .expect("Failed to join printing task")?;
+            return Ok(());
+        }
+        tick::deliver(start.elapsed());
+        let (did_rebuild, element) = update(&widget);
+        if let Some(copied) = clipboard::take_export() {
+            _ = frame_sender.send(RenderCommand::Write(clipboard::osc52(&copied)));
         }
-        thread::sleep(Duration::from_millis(10) - tick_start.elapsed());
+        if did_rebuild || resized {
+            _ = frame_sender.send(RenderCommand::Print(draw(element.as_ref(), size)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::{
+        message::MessageFlow,
+        prelude::{Harness, Point, column, row, text},
+        widget::prelude::*,
+    };
+
+    fn frame(rows: &[&str]) -> Frame {
+        rows.iter()
+            .map(|row| {
+                row.chars()
+                    .map(|c| Token::Char(c, Style::default()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn printed(prev: Option<&Frame>, frame: &Frame) -> String {
+        let mut out = vec![];
+        print_frame(prev, frame, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn first_frame_is_full_repaint() {
+        printed(None, &frame(&["ab", "cd"])).must_be("\x1b[2J\x1b[1;1Hab\x1b[2;1Hcd");
+    }
+
+    #[test]
+    fn unchanged_frame_prints_nothing() {
+        let f = frame(&["ab", "cd"]);
+        printed(Some(&f), &f).must_be("");
+    }
+
+    #[test]
+    fn single_cell_change() {
+        printed(Some(&frame(&["12", "  "])), &frame(&["13", "  "])).must_be("\x1b[1;2H3");
+    }
+
+    #[test]
+    fn runs_are_printed_together() {
+        printed(Some(&frame(&["abcdef"])), &frame(&["aXYdeZ"])).must_be("\x1b[1;2HXY\x1b[1;6HZ");
+    }
+
+    #[test]
+    fn changes_across_rows() {
+        printed(
+            Some(&frame(&["ab", "cd", "ef"])),
+            &frame(&["ab", "xd", "eg"]),
+        )
+        .must_be("\x1b[2;1Hx\x1b[3;2Hg");
+    }
+
+    #[test]
+    fn resize_is_full_repaint() {
+        printed(Some(&frame(&["ab"])), &frame(&["abc"])).must_be("\x1b[2J\x1b[1;1Habc");
+        printed(Some(&frame(&["ab"])), &frame(&["ab", "cd"]))
+            .must_be("\x1b[2J\x1b[1;1Hab\x1b[2;1Hcd");
+    }
+
+    #[test]
+    fn wide_glyphs_skip_their_continuation() {
+        let mut next = frame(&["abcd"]);
+        next[0][1] = Token::Char('中', Style::default());
+        next[0][2] = Token::Continuation;
+        printed(Some(&frame(&["abcd"])), &next).must_be("\x1b[1;2H中");
+        printed(Some(&next), &frame(&["abcd"])).must_be("\x1b[1;2Hbc");
+    }
+
+    #[test]
+    fn styles_switch_only_between_differing_cells() {
+        let bold = Style::default().bold();
+        let mut next = frame(&["abcd"]);
+        next[0][1] = Token::Char('b', bold);
+        next[0][2] = Token::Char('c', bold);
+        printed(Some(&frame(&["abcd"])), &next).must_be("\x1b[1;2H\x1b[1mbc\x1b[0m");
+        printed(Some(&next), &frame(&["abcd"])).must_be("\x1b[1;2Hbc");
+    }
+
+    #[test]
+    fn widgets_adapt_to_resizes() {
+        let responsive = Widget::stateful(
+            Size::default(),
+            |this, msg| {
+                switch(msg).case(|&Resize(size)| this.set_state(|state| *state = size));
+                MessageFlow::Propagate
+            },
+            |size| {
+                let children = [text("a"), text("b")];
+                if size.x < 10 {
+                    column(children)
+                } else {
+                    row(children)
+                }
+            },
+        );
+        let mut harness = Harness::mount(responsive, Size { x: 20, y: 2 });
+        harness.lines().must_be(vec!["a         b", ""]);
+        harness.resize(Size { x: 5, y: 2 });
+        harness.lines().must_be(vec!["a", "b"]);
+    }
+
+    #[test]
+    fn drawing_publishes_where_widgets_are() {
+        let below = text("b");
+        let id = below.borrow().id();
+        let (_, element) = update(&column([text("a"), below]));
+        draw(element.as_ref(), Size { x: 1, y: 2 });
+        mouse::region(id)
+            .map(|region| region.origin)
+            .must_be(Some(Point { x: 0, y: 1 }));
     }
 }
@@ -1,55 +1,155 @@
-use std::fmt::Display;
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug, Display},
+    io,
+    pin::Pin,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, RecvTimeoutError, Sender, channel},
+    },
+    task::{Context, Waker},
+    time::Duration,
+};
 
+use crossterm::event::Event;
 use tokio::{
-    sync::mpsc::UnboundedReceiver,
+    sync::{Notify, mpsc::error::SendError},
     task::{self, JoinError, JoinHandle},
 };
 
 pub mod prelude {
-    pub use super::{Task, go, go_block, log, wait_for};
+    pub use super::{
+        Overflow, StreamOptions, StreamSender, Task, Unstarted, go, go_block, log, wait_for,
+    };
 }
 
 thread_local! {
     pub static RT: tokio::runtime::Runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap();
+    static WAKE: (Sender<Wake>, Receiver<Wake>) = channel();
+}
+
+/// Why the render loop woke up
+#[derive(Debug)]
+pub(crate) enum Wake {
+    Event(io::Result<Event>),
+    /// A task finished, a stream produced a value or a message came in from another thread
+    Poll,
+}
+
+/// Sent to every widget after a `Wake::Poll`, so that future and stream widgets check their tasks
+pub(crate) struct Poll;
+
+/// Wakes the render loop of the current thread, from any thread
+pub(crate) fn waker() -> Sender<Wake> {
+    WAKE.with(|(sender, _)| sender.clone())
+}
+
+/// Waits until something wakes the render loop or `timeout` passes, then also takes whatever
+/// else has arrived in the meantime
+pub(crate) fn wait(timeout: Option<Duration>) -> Vec<Wake> {
+    WAKE.with(|(_, receiver)| {
+        let first = match timeout {
+            Some(timeout) => receiver.recv_timeout(timeout),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        first.into_iter().chain(receiver.try_iter()).collect()
+    })
 }
 
+/// Wakes the render loop when a task lets go of its work, whether it returned, panicked or was
+/// aborted. That is just before tokio marks the task finished, so `Task::check` makes sure the
+/// loop is woken again if it comes too early.
+struct WakeOnDrop(Sender<Wake>);
+
+impl Drop for WakeOnDrop {
+    fn drop(&mut self) {
+        _ = self.0.send(Wake::Poll);
+    }
+}
+
+/// Wakes the render loop when the `JoinHandle` it was polled with is ready
+struct LoopWaker(Sender<Wake>);
+
+impl std::task::Wake for LoopWaker {
+    fn wake(self: Arc<Self>) {
+        _ = self.0.send(Wake::Poll);
+    }
+}
+
+/// Runs `future` in the background and wakes the render loop once it is done
 pub fn go<T: 'static + Send + Sync>(
     future: impl Future<Output = T> + Send + Sync + 'static,
 ) -> JoinHandle<T> {
-    RT.with(|rt| rt.spawn(future))
+    let guard = WakeOnDrop(waker());
+    RT.with(|rt| {
+        rt.spawn(async move {
+            let _guard = guard;
+            future.await
+        })
+    })
 }
 
+/// Runs `f` on a thread for blocking work and wakes the render loop once it is done
 pub fn go_block<F, T>(f: F) -> JoinHandle<T>
 where
     F: FnOnce() -> T + Send + 'static,
     T: Send + 'static,
 {
-    RT.with(|rt| rt.spawn_blocking(f))
+    let guard = WakeOnDrop(waker());
+    RT.with(|rt| {
+        rt.spawn_blocking(move || {
+            let _guard = guard;
+            f()
+        })
+    })
+}
+
+/// A future that has not been started, which is discarded without ever running if dropped
+pub struct Unstarted<T>(Option<Pin<Box<dyn Future<Output = T> + Send + Sync>>>);
+
+impl<T> Debug for Unstarted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unstarted")
+    }
 }
 
 #[derive(Debug)]
 pub enum Task<T> {
+    /// Waiting to be started, as future widgets are until they are mounted
+    Pending(Unstarted<T>),
     Running(JoinHandle<T>),
     Done(T),
     Err(JoinError),
 }
 
+impl<T: 'static + Send + Sync> Task<T> {
+    pub fn pending(future: impl Future<Output = T> + Send + Sync + 'static) -> Self {
+        Task::Pending(Unstarted(Some(Box::pin(future))))
+    }
+    /// Runs a pending task with `go`, leaving one that was already started alone
+    pub fn start(&mut self) {
+        if let Task::Pending(Unstarted(future)) = self
+            && let Some(future) = future.take()
+        {
+            *self = Task::Running(go(future));
+        }
+    }
+}
+
 impl<T> Task<T> {
+    /// Takes the result of a finished task. One still running wakes the render loop once it is.
     pub fn check(&mut self) -> bool {
-        match self {
-            Self::Running(join_handle) => {
-                if join_handle.is_finished() {
-                    match wait_for(join_handle) {
-                        Ok(res) => *self = Task::Done(res),
-                        Err(err) => *self = Task::Err(err),
-                    }
-                    true
-                } else {
-                    false
-                }
-            }
-            _ => false,
+        let Self::Running(join_handle) = self else {
+            return false;
+        };
+        let waker = Waker::from(Arc::new(LoopWaker(waker())));
+        match Pin::new(join_handle).poll(&mut Context::from_waker(&waker)) {
+            std::task::Poll::Ready(Ok(res)) => *self = Task::Done(res),
+            std::task::Poll::Ready(Err(err)) => *self = Task::Err(err),
+            std::task::Poll::Pending => return false,
         }
+        true
     }
 }
 
@@ -62,29 +162,144 @@ impl<T> Drop for Task<T> {
     }
 }
 
-pub struct Stream<T, TaskRet> {
+/// What a bounded stream does with values sent while it is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Makes room by discarding the oldest value not yet received
+    DropOldest,
+    /// Discards the value being sent
+    DropNewest,
+    /// Waits in `send` until the widget has taken some values
+    #[default]
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamOptions {
+    /// How many values may wait to be received, or `None` for no limit
+    pub capacity: Option<usize>,
+    pub overflow: Overflow,
+}
+
+struct Queue<T> {
+    values: Mutex<VecDeque<T>>,
+    options: StreamOptions,
+    /// Notified when values are taken or the stream goes away
+    space: Notify,
+    closed: AtomicBool,
+    waker: Sender<Wake>,
+}
+
+/// Sends the values of a stream widget, waking the render loop
+pub struct StreamSender<T> {
+    queue: Arc<Queue<T>>,
+}
+
+impl<T> Clone for StreamSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<T> StreamSender<T> {
+    /// Queues `value` for the widget, following the stream's `Overflow` policy once it is full.
+    /// Fails once the widget is gone.
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let queue = &self.queue;
+        loop {
+            let space = queue.space.notified();
+            if queue.closed.load(Ordering::SeqCst) {
+                return Err(SendError(value));
+            }
+            {
+                let mut values = queue.values.lock().expect("A stream sender panicked");
+                let full = queue
+                    .options
+                    .capacity
+                    .is_some_and(|capacity| values.len() >= capacity);
+                if !full || queue.options.overflow != Overflow::Block {
+                    if full && queue.options.overflow == Overflow::DropNewest {
+                        return Ok(());
+                    }
+                    if full {
+                        values.pop_front();
+                    }
+                    values.push_back(value);
+                    // Otherwise a wakeup is already on its way
+                    if values.len() == 1 {
+                        _ = queue.waker.send(Wake::Poll);
+                    }
+                    return Ok(());
+                }
+            }
+            space.await;
+        }
+    }
+}
+
+type Fold<Acc, T> = Box<dyn Fn(&mut Acc, T)>;
+
+pub struct Stream<T, TaskRet, Acc = Option<T>> {
     pub task: Task<TaskRet>,
-    pub receiver: UnboundedReceiver<T>,
-    pub current: Option<T>,
+    queue: Arc<Queue<T>>,
+    /// Everything received so far, folded together
+    pub value: Acc,
+    fold: Fold<Acc, T>,
 }
 
-impl<T, TaskRet> Stream<T, TaskRet> {
+impl<T, TaskRet, Acc> Stream<T, TaskRet, Acc> {
+    pub(crate) fn new(
+        options: StreamOptions,
+        init: Acc,
+        fold: impl Fn(&mut Acc, T) + 'static,
+        generator: impl FnOnce(StreamSender<T>) -> JoinHandle<TaskRet>,
+    ) -> Self {
+        let queue = Arc::new(Queue {
+            values: Mutex::new(VecDeque::new()),
+            options,
+            space: Notify::new(),
+            closed: AtomicBool::new(false),
+            waker: waker(),
+        });
+        Self {
+            task: Task::Running(generator(StreamSender {
+                queue: queue.clone(),
+            })),
+            queue,
+            value: init,
+            fold: Box::new(fold),
+        }
+    }
+    /// Folds in every value that arrived since the last check
     pub fn check(&mut self) -> bool {
         let did_task_status_change = self.task.check();
-        if self.receiver.is_empty() {
-            did_task_status_change
-        } else {
-            self.current = self.receiver.blocking_recv();
-            true
+        let values =
+            std::mem::take(&mut *self.queue.values.lock().expect("A stream sender panicked"));
+        self.queue.space.notify_waiters();
+        let received = !values.is_empty();
+        for value in values {
+            (self.fold)(&mut self.value, value);
         }
+        did_task_status_change || received
+    }
+    pub fn value(&self) -> &Acc {
+        &self.value
     }
+}
+
+impl<T, TaskRet> Stream<T, TaskRet> {
+    /// The last value received
     pub fn current(&self) -> Option<&T> {
-        self.current.as_ref()
+        self.value.as_ref()
     }
 }
 
-impl<T, TaskRet> Drop for Stream<T, TaskRet> {
+impl<T, TaskRet, Acc> Drop for Stream<T, TaskRet, Acc> {
     fn drop(&mut self) {
+        self.queue.closed.store(true, Ordering::SeqCst);
+        self.queue.space.notify_waiters();
         match &mut self.task {
             Task::Running(handle) => handle.abort(),
             _ => {}
@@ -101,3 +316,152 @@ pub fn log<T: Display>(s: T) -> T {
     eprintln!("{s}");
     s
 }
+
+#[cfg(test)]
+mod test {
+    use std::{sync::mpsc, thread, time::Instant};
+
+    use stdext::prelude::Assertable;
+
+    use super::*;
+    use crate::{
+        message::prelude::*,
+        prelude::{Harness, Size, Widget, text},
+    };
+
+    #[test]
+    fn finished_tasks_wake_the_loop() {
+        wait(Some(Duration::ZERO));
+        let mut task = go(async { 1 });
+        wait_for(&mut task).ok().must_be(Some(1));
+        wait(Some(Duration::from_secs(1)))
+            .iter()
+            .any(|wake| matches!(wake, Wake::Poll))
+            .must_be(true);
+        wait(Some(Duration::ZERO)).len().must_be(0);
+    }
+
+    /// Whether checking `task` only when the loop wakes finds it finished
+    fn wakes_until_finished<T>(task: JoinHandle<T>) -> bool {
+        let mut task = Task::Running(task);
+        while !wait(Some(Duration::from_secs(1))).is_empty() {
+            if task.check() {
+                return true;
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn the_loop_wakes_until_tasks_are_finished() {
+        wait(Some(Duration::ZERO));
+        wakes_until_finished(go(async {})).must_be(true);
+        wakes_until_finished(go(async { panic!("The loop still wakes") })).must_be(true);
+        wakes_until_finished(go_block(|| {})).must_be(true);
+    }
+
+    /// A stream keeping everything it received, whose generator sends `0..count` once `start`
+    /// fires and reports on `done` when it is finished
+    fn received(
+        options: StreamOptions,
+        count: usize,
+    ) -> (
+        Harness,
+        tokio::sync::oneshot::Sender<()>,
+        mpsc::Receiver<()>,
+    ) {
+        let (start, started) = tokio::sync::oneshot::channel();
+        let (finished, done) = mpsc::channel();
+        let harness = Harness::mount(
+            Widget::stream_with(
+                options,
+                move |sender| async move {
+                    _ = started.await;
+                    for i in 0..count {
+                        _ = sender.send(i).await;
+                    }
+                    _ = finished.send(());
+                },
+                vec![],
+                |received: &mut Vec<usize>, i| received.push(i),
+                |_, _| Propagate,
+                |stream| text(format!("{:?}", stream.value())),
+            ),
+            Size { x: 40, y: 1 },
+        );
+        (harness, start, done)
+    }
+
+    fn bounded(overflow: Overflow) -> StreamOptions {
+        StreamOptions {
+            capacity: Some(2),
+            overflow,
+        }
+    }
+
+    #[test]
+    fn everything_pending_is_taken_in_one_frame() {
+        let (mut harness, start, done) = received(StreamOptions::default(), 5);
+        start.send(()).unwrap();
+        done.recv().unwrap();
+        harness.tick(Duration::ZERO);
+        harness.lines().must_be(vec!["[0, 1, 2, 3, 4]"]);
+    }
+
+    #[test]
+    fn full_streams_drop_the_oldest_or_newest_values() {
+        let (mut harness, start, done) = received(bounded(Overflow::DropOldest), 5);
+        start.send(()).unwrap();
+        done.recv().unwrap();
+        harness.tick(Duration::ZERO);
+        harness.lines().must_be(vec!["[3, 4]"]);
+
+        let (mut harness, start, done) = received(bounded(Overflow::DropNewest), 5);
+        start.send(()).unwrap();
+        done.recv().unwrap();
+        harness.tick(Duration::ZERO);
+        harness.lines().must_be(vec!["[0, 1]"]);
+    }
+
+    #[test]
+    fn full_streams_can_hold_the_producer_back() {
+        let (mut harness, start, done) = received(bounded(Overflow::Block), 5);
+        start.send(()).unwrap();
+        let began = Instant::now();
+        while done.try_recv().is_err() && began.elapsed() < Duration::from_secs(5) {
+            harness.tick(Duration::ZERO);
+            thread::sleep(Duration::from_millis(1));
+        }
+        harness.tick(Duration::ZERO);
+        harness.lines().must_be(vec!["[0, 1, 2, 3, 4]"]);
+    }
+
+    #[test]
+    fn folds_can_keep_the_last_lines() {
+        let (finished, done) = mpsc::channel();
+        let mut harness = Harness::mount(
+            Widget::stream_with(
+                StreamOptions::default(),
+                move |sender| async move {
+                    for line in ["a", "b", "c", "d"] {
+                        _ = sender.send(line).await;
+                    }
+                    _ = finished.send(());
+                },
+                VecDeque::new(),
+                |tail, line| {
+                    tail.push_back(line);
+                    if tail.len() > 2 {
+                        tail.pop_front();
+                    }
+                },
+                |_, _| Propagate,
+                |stream| text(stream.value().iter().copied().collect::<Vec<_>>().join(" ")),
+            ),
+            Size { x: 5, y: 1 },
+        );
+        done.recv().unwrap();
+        harness.tick(Duration::ZERO);
+        harness.lines().must_be(vec!["c d"]);
+    }
+}
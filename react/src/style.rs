@@ -0,0 +1,290 @@
+pub mod prelude {
+    pub use super::{Color, Style};
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+    /// One of the 256 colors of the xterm palette
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// The red, green and blue a terminal with the xterm palette shows this color in
+    pub fn rgb(self) -> (u8, u8, u8) {
+        const NAMED: [(u8, u8, u8); 16] = [
+            (0, 0, 0),
+            (205, 0, 0),
+            (0, 205, 0),
+            (205, 205, 0),
+            (0, 0, 238),
+            (205, 0, 205),
+            (0, 205, 205),
+            (229, 229, 229),
+            (127, 127, 127),
+            (255, 0, 0),
+            (0, 255, 0),
+            (255, 255, 0),
+            (92, 92, 255),
+            (255, 0, 255),
+            (0, 255, 255),
+            (255, 255, 255),
+        ];
+        const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+        let index = match self {
+            Color::Black => 0,
+            Color::Red => 1,
+            Color::Green => 2,
+            Color::Yellow => 3,
+            Color::Blue => 4,
+            Color::Magenta => 5,
+            Color::Cyan => 6,
+            Color::White => 7,
+            Color::BrightBlack => 8,
+            Color::BrightRed => 9,
+            Color::BrightGreen => 10,
+            Color::BrightYellow => 11,
+            Color::BrightBlue => 12,
+            Color::BrightMagenta => 13,
+            Color::BrightCyan => 14,
+            Color::BrightWhite => 15,
+            Color::Indexed(i) => i,
+            Color::Rgb(r, g, b) => return (r, g, b),
+        };
+        match index {
+            0..16 => NAMED[index as usize],
+            16..232 => {
+                let i = (index - 16) as usize;
+                (LEVELS[i / 36], LEVELS[i / 6 % 6], LEVELS[i % 6])
+            }
+            _ => {
+                let gray = 8 + 10 * (index - 232);
+                (gray, gray, gray)
+            }
+        }
+    }
+    /// SGR parameters selecting this color, `base` being 30 for foreground and 40 for background
+    fn sgr(self, base: u8) -> String {
+        let named = |i: u8| (base + i).to_string();
+        let bright = |i: u8| (base + 60 + i).to_string();
+        match self {
+            Color::Black => named(0),
+            Color::Red => named(1),
+            Color::Green => named(2),
+            Color::Yellow => named(3),
+            Color::Blue => named(4),
+            Color::Magenta => named(5),
+            Color::Cyan => named(6),
+            Color::White => named(7),
+            Color::BrightBlack => bright(0),
+            Color::BrightRed => bright(1),
+            Color::BrightGreen => bright(2),
+            Color::BrightYellow => bright(3),
+            Color::BrightBlue => bright(4),
+            Color::BrightMagenta => bright(5),
+            Color::BrightCyan => bright(6),
+            Color::BrightWhite => bright(7),
+            Color::Indexed(i) => format!("{};5;{i}", base + 8),
+            Color::Rgb(r, g, b) => format!("{};2;{r};{g};{b}", base + 8),
+        }
+    }
+}
+
+/// Styling of a single terminal cell. `None` colors mean the terminal's default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+impl Style {
+    pub fn fg(self, color: Color) -> Self {
+        Self {
+            fg: Some(color),
+            ..self
+        }
+    }
+    pub fn bg(self, color: Color) -> Self {
+        Self {
+            bg: Some(color),
+            ..self
+        }
+    }
+    pub fn bold(self) -> Self {
+        Self { bold: true, ..self }
+    }
+    pub fn dim(self) -> Self {
+        Self { dim: true, ..self }
+    }
+    pub fn italic(self) -> Self {
+        Self {
+            italic: true,
+            ..self
+        }
+    }
+    pub fn underline(self) -> Self {
+        Self {
+            underline: true,
+            ..self
+        }
+    }
+    pub fn reverse(self) -> Self {
+        Self {
+            reverse: true,
+            ..self
+        }
+    }
+
+    /// Layers `other` on top of `self`: colors set in `other` win, attributes accumulate.
+    pub fn patch(self, other: Style) -> Self {
+        Self {
+            fg: other.fg.or(self.fg),
+            bg: other.bg.or(self.bg),
+            bold: self.bold || other.bold,
+            dim: self.dim || other.dim,
+            italic: self.italic || other.italic,
+            underline: self.underline || other.underline,
+            reverse: self.reverse || other.reverse,
+        }
+    }
+
+    fn attributes(&self) -> [(bool, &'static str, &'static str); 3] {
+        [
+            (self.italic, "3", "23"),
+            (self.underline, "4", "24"),
+            (self.reverse, "7", "27"),
+        ]
+    }
+
+    /// Parameters that turn a terminal in the default state into `self`
+    fn params(&self) -> Vec<String> {
+        let mut params = vec![];
+        if self.bold {
+            params.push("1".to_string());
+        }
+        if self.dim {
+            params.push("2".to_string());
+        }
+        for (on, set, _) in self.attributes() {
+            if on {
+                params.push(set.to_string());
+            }
+        }
+        params.extend(self.fg.map(|fg| fg.sgr(30)));
+        params.extend(self.bg.map(|bg| bg.sgr(40)));
+        params
+    }
+
+    /// The shortest SGR escape sequence that turns a terminal currently printing in `self`
+    /// into one printing in `to`. Empty if the styles are equal.
+    pub fn transition(&self, to: &Style) -> String {
+        if self == to {
+            return String::new();
+        }
+        let mut params = vec![];
+        // Bold and dim are both turned off by 22, so the one that stays needs to be set again
+        let (was_bold, was_dim) = if (self.bold && !to.bold) || (self.dim && !to.dim) {
+            params.push("22".to_string());
+            (false, false)
+        } else {
+            (self.bold, self.dim)
+        };
+        if to.bold && !was_bold {
+            params.push("1".to_string());
+        }
+        if to.dim && !was_dim {
+            params.push("2".to_string());
+        }
+        for ((was, _, unset), (is, set, _)) in self.attributes().into_iter().zip(to.attributes()) {
+            if was && !is {
+                params.push(unset.to_string());
+            } else if !was && is {
+                params.push(set.to_string());
+            }
+        }
+        if self.fg != to.fg {
+            params.push(to.fg.map_or("39".to_string(), |fg| fg.sgr(30)));
+        }
+        if self.bg != to.bg {
+            params.push(to.bg.map_or("49".to_string(), |bg| bg.sgr(40)));
+        }
+        let reset = ["0".to_string()]
+            .into_iter()
+            .chain(to.params())
+            .collect::<Vec<_>>();
+        let params = if reset.join(";").len() < params.join(";").len() {
+            reset
+        } else {
+            params
+        };
+        format!("\x1b[{}m", params.join(";"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn equal_styles_need_no_escape() {
+        Style::default()
+            .bold()
+            .transition(&Style::default().bold())
+            .must_be("");
+    }
+
+    #[test]
+    fn only_changed_attributes_are_emitted() {
+        let red = Style::default().fg(Color::Red);
+        red.transition(&red.underline()).must_be("\x1b[4m");
+        red.underline().transition(&red).must_be("\x1b[24m");
+        red.transition(&red.bg(Color::Rgb(1, 2, 3)))
+            .must_be("\x1b[48;2;1;2;3m");
+        red.transition(&Style::default().fg(Color::Indexed(208)))
+            .must_be("\x1b[38;5;208m");
+    }
+
+    #[test]
+    fn unsetting_bold_keeps_dim() {
+        let red = Style::default().fg(Color::Red);
+        red.bold()
+            .dim()
+            .transition(&red.dim())
+            .must_be("\x1b[22;2m");
+    }
+
+    #[test]
+    fn resets_when_shorter() {
+        let busy = Style::default()
+            .fg(Color::Red)
+            .bg(Color::Blue)
+            .bold()
+            .underline();
+        busy.transition(&Style::default()).must_be("\x1b[0m");
+        busy.transition(&Style::default().fg(Color::BrightGreen))
+            .must_be("\x1b[0;92m");
+    }
+}
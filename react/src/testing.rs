@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+use crate::{
+    clipboard::Paste,
+    component::prelude::*,
+    displaylist::CURSOR_COLOR,
+    frame::{Frame, Token},
+    keymap, lifecycle,
+    message::{send, send_to},
+    mouse,
+    prelude::{Point, Rect, Size},
+    render::{Resize, dispatch, draw, update},
+    runtime::{self, Poll},
+    tick,
+    unicode::{graphemes, width},
+};
+
+pub mod prelude {
+    pub use super::Harness;
+}
+
+/// Drives a component without a terminal, for tests.
+/// Every interaction runs one message/rebuild cycle and redraws the frame.
+pub struct Harness {
+    root: Component,
+    size: Size,
+    elapsed: Duration,
+    frame: Frame,
+}
+
+impl Harness {
+    pub fn mount(root: Component, size: Size) -> Self {
+        let mut harness = Self {
+            root,
+            size,
+            elapsed: Duration::ZERO,
+            frame: vec![],
+        };
+        harness.send(Resize(size));
+        harness
+    }
+
+    /// Unmounts the whole tree, as when the app stops
+    pub fn unmount(&mut self) -> &mut Self {
+        lifecycle::unmount(&self.root);
+        self
+    }
+
+    fn step(&mut self) -> &mut Self {
+        let (_, element) = update(&self.root);
+        self.frame = draw(element.as_ref(), self.size);
+        self
+    }
+
+    pub fn send<T: 'static>(&mut self, message: T) -> &mut Self {
+        send(message);
+        self.step()
+    }
+
+    pub fn send_to<T: 'static>(&mut self, id: usize, message: T) -> &mut Self {
+        send_to(id, message);
+        self.step()
+    }
+
+    /// Presses a key, which the keymaps in effect may turn into an `Action`
+    pub fn key(&mut self, event: KeyEvent) -> &mut Self {
+        dispatch(keymap::resolve(event, self.elapsed));
+        self.step()
+    }
+
+    pub fn press(&mut self, code: KeyCode) -> &mut Self {
+        self.key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    pub fn press_with(&mut self, modifiers: KeyModifiers, code: KeyCode) -> &mut Self {
+        self.key(KeyEvent::new(code, modifiers))
+    }
+
+    pub fn type_str(&mut self, s: &str) -> &mut Self {
+        s.chars().for_each(|c| _ = self.press(KeyCode::Char(c)));
+        self
+    }
+
+    pub fn paste(&mut self, s: &str) -> &mut Self {
+        self.send(Paste(s.to_string()))
+    }
+
+    pub fn mouse(&mut self, kind: MouseEventKind, at: Point) -> &mut Self {
+        mouse::route(MouseEvent {
+            kind,
+            column: at.x as u16,
+            row: at.y as u16,
+            modifiers: KeyModifiers::NONE,
+        });
+        self.step()
+    }
+
+    pub fn click(&mut self, at: Point) -> &mut Self {
+        self.mouse(MouseEventKind::Down(MouseButton::Left), at)
+            .mouse(MouseEventKind::Up(MouseButton::Left), at)
+    }
+
+    /// Lets `by` pass, delivering the `Tick`s that came due and checking on background tasks
+    pub fn tick(&mut self, by: Duration) -> &mut Self {
+        self.elapsed += by;
+        dispatch(keymap::expire(self.elapsed));
+        tick::deliver(self.elapsed);
+        runtime::wait(Some(Duration::ZERO));
+        self.send(Poll)
+    }
+
+    pub fn resize(&mut self, size: Size) -> &mut Self {
+        self.size = size;
+        self.send(Resize(size))
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    pub fn cell(&self, at: Point) -> Option<&Token> {
+        if at.x < 0 || at.y < 0 {
+            return None;
+        }
+        self.frame.get(at.y as usize)?.get(at.x as usize)
+    }
+
+    /// Every row as plain text without styles or trailing spaces
+    pub fn lines(&self) -> Vec<String> {
+        self.frame
+            .iter()
+            .map(|row| {
+                let mut line = String::new();
+                for token in row {
+                    match token {
+                        Token::Char(c, _) => line.push(*c),
+                        Token::Cluster(cluster, _) => line.push_str(cluster),
+                        Token::Continuation => {}
+                    }
+                }
+                line.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    /// The frame as plain text, with trailing empty lines removed
+    pub fn text(&self) -> String {
+        self.lines().join("\n").trim_end().to_string()
+    }
+
+    /// The cells holding the first occurrence of `needle` within a single row
+    pub fn find(&self, needle: &str) -> Option<Rect> {
+        let needle = graphemes(needle).collect::<Vec<_>>();
+        if needle.is_empty() {
+            return None;
+        }
+        self.frame.iter().enumerate().find_map(|(y, row)| {
+            let cells = row
+                .iter()
+                .enumerate()
+                .filter_map(|(x, token)| match token {
+                    Token::Char(c, _) => Some((x, c.to_string())),
+                    Token::Cluster(cluster, _) => Some((x, cluster.to_string())),
+                    Token::Continuation => None,
+                })
+                .collect::<Vec<_>>();
+            cells.windows(needle.len()).find_map(|window| {
+                if !window
+                    .iter()
+                    .map(|(_, g)| g.as_str())
+                    .eq(needle.iter().copied())
+                {
+                    return None;
+                }
+                let (start, _) = window[0];
+                let (end, last) = &window[window.len() - 1];
+                Some(Rect {
+                    origin: Point {
+                        x: start as isize,
+                        y: y as isize,
+                    },
+                    size: Size {
+                        x: (end + width(last).max(1) - start) as isize,
+                        y: 1,
+                    },
+                })
+            })
+        })
+    }
+
+    /// Where a cursor was last drawn
+    pub fn cursor(&self) -> Option<Point> {
+        self.frame.iter().enumerate().find_map(|(y, row)| {
+            row.iter()
+                .position(|token| token.style().and_then(|style| style.bg) == Some(CURSOR_COLOR))
+                .map(|x| Point {
+                    x: x as isize,
+                    y: y as isize,
+                })
+        })
+    }
+}
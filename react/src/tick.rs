@@ -0,0 +1,172 @@
+use std::{cell::RefCell, collections::HashMap, time::Duration};
+
+use crate::{component::prelude::*, message::send_to};
+
+pub mod prelude {
+    pub use super::{Tick, ticking};
+}
+
+/// Time since the app started, sent to widgets that asked for it with `ticking`
+pub struct Tick(pub Duration);
+
+#[derive(Debug, Default)]
+struct Schedule {
+    /// Widgets asking for ticks during the last pass, with how often they want them
+    subscribers: Vec<(usize, Duration)>,
+    /// When each subscriber gets its next tick
+    due: HashMap<usize, Duration>,
+    /// When ticks were last delivered, which new subscribers count from
+    last: Duration,
+}
+
+thread_local! {
+    static SCHEDULE: RefCell<Schedule> = RefCell::new(Schedule::default());
+}
+
+/// Sends `component` a `Tick` every `interval`, at multiples of it since the app started.
+/// Rebuilds that reuse `component` keep the interval it was mounted with; `keyed` by the
+/// interval, it is replaced whenever that changes.
+pub fn ticking(component: Component, interval: Duration) -> Component {
+    component.borrow_mut().set_tick_interval(Some(interval));
+    component
+}
+
+pub(crate) fn begin() {
+    SCHEDULE.with_borrow_mut(|schedule| schedule.subscribers.clear());
+}
+
+pub(crate) fn register(id: usize, interval: Duration) {
+    SCHEDULE.with_borrow_mut(|schedule| schedule.subscribers.push((id, interval)));
+}
+
+/// The first multiple of `interval` after `now`, or the end of time if that is too far out
+fn next(now: Duration, interval: Duration) -> Duration {
+    let interval = interval.max(Duration::from_millis(1)).as_nanos();
+    let nanos = (now.as_nanos() / interval + 1) * interval;
+    u64::try_from(nanos).map_or(Duration::MAX, Duration::from_nanos)
+}
+
+/// Sends `Tick`s to the subscribers that are due at `now`
+pub(crate) fn deliver(now: Duration) {
+    SCHEDULE.with_borrow_mut(|schedule| {
+        let Schedule {
+            subscribers,
+            due,
+            last,
+        } = schedule;
+        due.retain(|id, _| subscribers.iter().any(|(subscriber, _)| subscriber == id));
+        for &(id, interval) in subscribers.iter() {
+            let due = due.entry(id).or_insert_with(|| next(*last, interval));
+            if now >= *due {
+                send_to(id, Tick(now));
+                *due = next(now, interval);
+            }
+        }
+        *last = now;
+    });
+}
+
+/// When the next tick is due, if anyone is asking for them
+pub(crate) fn next_due() -> Option<Duration> {
+    SCHEDULE.with_borrow(|schedule| {
+        schedule
+            .subscribers
+            .iter()
+            .map(|&(id, interval)| {
+                schedule
+                    .due
+                    .get(&id)
+                    .copied()
+                    .unwrap_or_else(|| next(schedule.last, interval))
+            })
+            .min()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, rc::Rc};
+
+    use crossterm::event::{KeyCode, KeyEvent};
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::{
+        message::prelude::*,
+        prelude::{Harness, Size, column, keyed, text},
+        widget::prelude::*,
+    };
+
+    fn counting(count: Rc<Cell<usize>>) -> Component {
+        Widget::stateful(
+            (),
+            move |_, msg| {
+                switch(msg).case(|_: &Tick| count.set(count.get() + 1));
+                Propagate
+            },
+            |_| text(""),
+        )
+    }
+
+    #[test]
+    fn ticks_come_at_the_requested_rate() {
+        let (fast, slow, idle) = (Rc::default(), Rc::default(), Rc::default());
+        let mut harness = Harness::mount(
+            column([
+                ticking(counting(Rc::clone(&fast)), Duration::from_millis(100)),
+                ticking(counting(Rc::clone(&slow)), Duration::from_millis(300)),
+                counting(Rc::clone(&idle)),
+            ]),
+            Size { x: 1, y: 3 },
+        );
+        next_due().must_be(Some(Duration::from_millis(100)));
+        for _ in 0..6 {
+            harness.tick(Duration::from_millis(100));
+        }
+        [fast.get(), slow.get(), idle.get()].must_be([6, 2, 0]);
+        next_due().must_be(Some(Duration::from_millis(700)));
+    }
+
+    #[test]
+    fn rebuilds_keep_the_rate_unless_keyed() {
+        let (kept, restarted) = (Rc::<Cell<usize>>::default(), Rc::<Cell<usize>>::default());
+        let (a, b) = (Rc::clone(&kept), Rc::clone(&restarted));
+        let mut harness = Harness::mount(
+            Widget::stateful(
+                Duration::from_millis(100),
+                |this, msg| {
+                    switch(msg).case(|_: &KeyEvent| {
+                        this.set_state(|interval| *interval = Duration::from_millis(300))
+                    });
+                    Propagate
+                },
+                move |&interval| {
+                    column([
+                        ticking(counting(Rc::clone(&a)), interval),
+                        keyed(interval, ticking(counting(Rc::clone(&b)), interval)),
+                    ])
+                },
+            ),
+            Size { x: 1, y: 2 },
+        );
+        harness.tick(Duration::from_millis(100));
+        harness.press(KeyCode::Char('x'));
+        for _ in 0..3 {
+            harness.tick(Duration::from_millis(100));
+        }
+        [kept.get(), restarted.get()].must_be([4, 2]);
+    }
+
+    #[test]
+    fn ticks_stay_ahead_after_running_for_months() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        next(day * 50, Duration::from_millis(1)).must_be(day * 50 + Duration::from_millis(1));
+        next(Duration::MAX, Duration::from_secs(1)).must_be(Duration::MAX);
+    }
+
+    #[test]
+    fn nothing_is_due_without_subscribers() {
+        let _harness = Harness::mount(text("idle"), Size { x: 4, y: 1 });
+        next_due().must_be(None);
+    }
+}
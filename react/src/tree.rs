@@ -0,0 +1,57 @@
+use std::{cell::RefCell, collections::HashMap};
+
+/// Shape of the component tree as seen during the last `create_element` pass
+#[derive(Debug, Default)]
+pub(crate) struct Tree {
+    parents: HashMap<usize, usize>,
+    /// Ids in the order they were visited, parents before their children
+    order: Vec<usize>,
+    stack: Vec<usize>,
+}
+
+thread_local! {
+    pub(crate) static TREE: RefCell<Tree> = RefCell::new(Tree::default());
+}
+
+/// Forgets the previous pass; called before the root creates its element
+pub(crate) fn begin() {
+    TREE.with_borrow_mut(|tree| *tree = Tree::default());
+}
+
+pub(crate) fn enter(id: usize) {
+    TREE.with_borrow_mut(|tree| {
+        if let Some(&parent) = tree.stack.last() {
+            tree.parents.insert(id, parent);
+        }
+        tree.order.push(id);
+        tree.stack.push(id);
+    });
+}
+
+pub(crate) fn exit() {
+    TREE.with_borrow_mut(|tree| tree.stack.pop());
+}
+
+pub fn parent(id: usize) -> Option<usize> {
+    TREE.with_borrow(|tree| tree.parents.get(&id).copied())
+}
+
+/// `id` followed by its parent, grandparent, ... up to the root
+pub fn ancestors(id: usize) -> Vec<usize> {
+    TREE.with_borrow(|tree| {
+        let mut ancestors = vec![id];
+        while let Some(parent) = tree.parents.get(ancestors.last().unwrap()) {
+            ancestors.push(*parent);
+        }
+        ancestors
+    })
+}
+
+pub fn is_ancestor(ancestor: usize, of: usize) -> bool {
+    ancestors(of).contains(&ancestor)
+}
+
+/// Whether `id` was visited in the last pass
+pub fn is_mounted(id: usize) -> bool {
+    TREE.with_borrow(|tree| tree.order.contains(&id))
+}
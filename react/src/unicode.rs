@@ -0,0 +1,53 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Splits `s` into the user-perceived characters that each go into one (or two) terminal cells
+pub fn graphemes(s: &str) -> impl Iterator<Item = &str> {
+    s.graphemes(true)
+}
+
+/// Number of terminal cells `s` takes up when printed
+pub fn width(s: &str) -> usize {
+    s.width()
+}
+
+pub fn is_newline(grapheme: &str) -> bool {
+    matches!(grapheme, "\n" | "\r\n")
+}
+
+/// The longest prefix of `s` that fits into `cells`, ending in '…' if it had to be cut
+pub fn truncate(s: &str, cells: usize) -> String {
+    if width(s) <= cells {
+        return s.to_string();
+    }
+    if cells == 0 {
+        return String::new();
+    }
+    let mut truncated = String::new();
+    let mut used = 0;
+    for g in graphemes(s) {
+        if used + width(g) + 1 > cells {
+            break;
+        }
+        used += width(g);
+        truncated.push_str(g);
+    }
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn truncation_leaves_room_for_the_ellipsis() {
+        truncate("title", 5).must_be("title");
+        truncate("title", 4).must_be("tit…");
+        truncate("中文字", 4).must_be("中…");
+        truncate("title", 1).must_be("…");
+        truncate("title", 0).must_be("");
+    }
+}
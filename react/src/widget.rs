@@ -1,14 +1,28 @@
-use std::{cell::RefCell, fmt::Debug, ops::RangeFrom, rc::Rc};
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    fmt::Debug,
+    ops::RangeFrom,
+    rc::Rc,
+    time::Duration,
+};
 
-use stdext::prelude::switch;
-use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+use crossterm::event::KeyEvent;
+use stdext::prelude::{any, switch};
 
 use crate::{
+    clipboard::Paste,
     component::prelude::*,
-    message::prelude::*,
-    prelude::Element,
-    render::Tick,
-    runtime::{Stream, Task, go},
+    focus,
+    hooks::Hooks,
+    keymap::{self, Action, Keymap},
+    lifecycle,
+    message::{self, Handle, prelude::*},
+    mouse::Mouse,
+    prelude::{Element, RegionElement},
+    reconcile::reconcile,
+    runtime::{Poll, Stream, StreamOptions, StreamSender, Task, go},
+    tick, tree,
 };
 
 pub mod prelude {
@@ -23,14 +37,34 @@ pub fn uid() -> usize {
     COUNTER.with_borrow_mut(|counter| counter.next().expect("Ran out of UIDs for Widgets"))
 }
 
+type OnMessage<State> = Rc<dyn Fn(&mut Widget<State>, &Message) -> MessageFlow>;
+type CreateElement<State> = Rc<dyn Fn(&mut Widget<State>) -> (bool, Box<dyn Element>)>;
+
 pub struct Widget<State> {
     id: usize,
     pub state: State,
     prev: Option<Component>,
     needs_rebuild: bool,
     builder: Box<dyn Fn(&State) -> Component>,
-    on_message: Rc<dyn Fn(&mut Self, &Message)>,
-    create_element: Rc<dyn Fn(&mut Self) -> (bool, Box<dyn Element>)>,
+    /// Handles a message for this widget alone; `Propagate` passes it on to the children
+    on_message: OnMessage<State>,
+    create_element: CreateElement<State>,
+    focusable: bool,
+    keymap: Option<Rc<Keymap>>,
+    tick_interval: Option<Duration>,
+    hooks: Rc<Hooks>,
+    mounted: bool,
+    on_mount: Vec<Box<dyn FnMut()>>,
+    on_unmount: Vec<Box<dyn FnMut()>>,
+    key: Option<u64>,
+    kind: TypeId,
+    children: fn(&mut State) -> Vec<&mut Component>,
+    /// Brings the state of a fresh instance into this one when a rebuild reuses it
+    merge: fn(&mut State, &mut State),
+    /// Starts whatever the state is to run, on mount
+    start: fn(&mut State),
+    /// Stops whatever the state keeps running, on unmount
+    release: fn(&mut State),
 }
 
 impl<State> Debug for Widget<State> {
@@ -48,90 +82,163 @@ impl<State> Widget<State>
 where
     State: 'static,
 {
-    pub fn stateful(
+    fn create(
         state: State,
-        on_message: impl Fn(&mut Self, &Message) -> MessageFlow + 'static,
-        builder: impl Fn(&State) -> Component + 'static,
-    ) -> Component {
-        Rc::new(RefCell::new(Widget {
+        kind: TypeId,
+        builder: Box<dyn Fn(&State) -> Component>,
+        on_message: OnMessage<State>,
+        create_element: CreateElement<State>,
+        children: fn(&mut State) -> Vec<&mut Component>,
+    ) -> Self {
+        Widget {
             id: uid(),
-            state: state,
+            state,
             prev: None,
             needs_rebuild: true,
-            builder: Box::new(builder),
-            on_message: Rc::new(move |this, msg| {
-                if let Propagate = on_message(this, msg)
-                    && let Some(prev) = &this.prev
-                {
-                    prev.borrow_mut().on_message(msg);
-                }
-            }),
-            create_element: Rc::new(create_child),
-        }))
+            builder,
+            on_message,
+            create_element,
+            focusable: false,
+            keymap: None,
+            tick_interval: None,
+            hooks: Rc::default(),
+            mounted: false,
+            on_mount: vec![],
+            on_unmount: vec![],
+            key: None,
+            kind,
+            children,
+            merge: |_, _| {},
+            start: |_| {},
+            release: |_| {},
+        }
     }
-    pub fn elemental(
+    fn component(self) -> Component {
+        Rc::new(RefCell::new(self))
+    }
+    pub fn stateful<B: Fn(&State) -> Component + 'static>(
+        state: State,
+        on_message: impl Fn(&mut Self, &Message) -> MessageFlow + 'static,
+        builder: B,
+    ) -> Component {
+        Self::create(
+            state,
+            TypeId::of::<B>(),
+            Box::new(builder),
+            Rc::new(on_message),
+            Rc::new(create_child),
+            no_children,
+        )
+        .component()
+    }
+    pub fn elemental<E: Fn(&mut Self) -> (bool, Box<dyn Element>) + 'static>(
         state: State,
         on_message: impl Fn(&mut Self, &Message) + 'static,
-        create_element: impl Fn(&mut Self) -> (bool, Box<dyn Element>) + 'static,
+        create_element: E,
     ) -> Component {
-        Rc::new(RefCell::new(Widget {
-            id: uid(),
-            state: state,
-            prev: None,
-            needs_rebuild: true,
-            builder: Box::new(|_| panic!()),
-            on_message: Rc::new(on_message),
-            create_element: Rc::new(create_element),
-        }))
+        Self::container(
+            state,
+            no_children,
+            move |this, msg| {
+                on_message(this, msg);
+                Propagate
+            },
+            create_element,
+        )
+    }
+    /// An elemental widget whose state holds child components, which `children` exposes so
+    /// that rebuilds of an enclosing widget can reconcile them with the previous ones and
+    /// messages reach them unless `on_message` intercepts them
+    pub fn container<E: Fn(&mut Self) -> (bool, Box<dyn Element>) + 'static>(
+        state: State,
+        children: fn(&mut State) -> Vec<&mut Component>,
+        on_message: impl Fn(&mut Self, &Message) -> MessageFlow + 'static,
+        create_element: E,
+    ) -> Component {
+        Widget {
+            merge: std::mem::swap,
+            ..Self::create(
+                state,
+                TypeId::of::<E>(),
+                Box::new(|_| panic!()),
+                Rc::new(on_message),
+                Rc::new(create_element),
+                children,
+            )
+        }
+        .component()
     }
     fn _build(&mut self) -> (bool, Component) {
         if !self.needs_rebuild
+            && !self.hooks.is_dirty()
             && let Some(prev) = &self.prev
         {
             (false, prev.clone())
         } else {
-            let new_widget = (self.builder)(&self.state);
+            let mut new_widget = self.hooks.clone().build(|| (self.builder)(&self.state));
+            if let Some(prev) = &self.prev {
+                let before = lifecycle::subtree(prev);
+                new_widget = reconcile(prev, new_widget);
+                lifecycle::detach(before, &new_widget);
+            }
             self.prev = Some(new_widget.clone());
             self.needs_rebuild = false;
             (true, new_widget)
         }
     }
+    /// Addresses this widget from async tasks and other threads
+    pub fn handle(&self) -> Handle {
+        message::handle(self.id)
+    }
     #[inline]
     pub fn set_state(&mut self, f: impl FnOnce(&mut State)) {
         f(&mut self.state);
         self.needs_rebuild = true;
     }
+    pub(crate) fn set_merge(&mut self, merge: fn(&mut State, &mut State)) {
+        self.merge = merge;
+    }
 }
 
 impl<T: 'static + Send + Sync> Widget<Task<T>> {
-    pub fn future(
+    /// Shows how `task` is doing, starting it once mounted. Rebuilds of the parent leave the
+    /// running task alone; `keyed` by its inputs, it starts over whenever they change.
+    pub fn future<B: Fn(&Task<T>) -> Component + 'static>(
         task: impl Future<Output = T> + Send + Sync + 'static,
         on_message: impl Fn(&mut Self, &Message) -> MessageFlow + 'static,
-        builder: impl Fn(&Task<T>) -> Component + 'static,
+        builder: B,
     ) -> Component {
-        Rc::new(RefCell::new(Widget {
-            id: uid(),
-            state: Task::Running(go(task)),
-            prev: None,
-            needs_rebuild: true,
-            builder: Box::new(builder),
-            on_message: Rc::new(move |this, msg| {
-                switch(msg).case(|&Tick(_)| {
-                    if this.state.check() {
-                        this.set_state(|_| {});
-                    }
-                });
-                if let Propagate = on_message(this, msg)
-                    && let Some(prev) = &this.prev
-                {
-                    prev.borrow_mut().on_message(msg);
+        Widget {
+            start: Task::start,
+            release: |task| {
+                if let Task::Running(task) = task {
+                    task.abort();
                 }
-            }),
-            create_element: Rc::new(create_child),
-        }))
+            },
+            ..Self::create(
+                Task::pending(task),
+                TypeId::of::<B>(),
+                Box::new(builder),
+                Rc::new(move |this, msg| {
+                    switch(msg).case(|_: &Poll| {
+                        if this.state.check() {
+                            this.set_state(|_| {});
+                        }
+                    });
+                    on_message(this, msg)
+                }),
+                Rc::new(create_child),
+                no_children,
+            )
+        }
+        .component()
     }
 }
 
+fn no_children<State>(_: &mut State) -> Vec<&mut Component> {
+    vec![]
+}
+
 fn create_child<T: 'static>(this: &mut Widget<T>) -> (bool, Box<dyn Element>) {
     let (did_rebuild, widget) = this._build();
     let (did_child_rebuild, child_element) = widget.borrow_mut().create_element();
@@ -139,51 +246,219 @@ fn create_child<T: 'static>(this: &mut Widget<T>) -> (bool, Box<dyn Element>) {
 }
 
 impl<T: 'static + Send + Sync, TaskRet: Send + Sync + 'static> Widget<Stream<T, TaskRet>> {
-    pub fn stream<F: Future<Output = TaskRet> + Send + Sync + 'static>(
-        generator: impl FnOnce(UnboundedSender<T>) -> F,
+    /// Shows the last value `generator` sent, unbounded
+    pub fn stream<
+        F: Future<Output = TaskRet> + Send + Sync + 'static,
+        B: Fn(&Stream<T, TaskRet>) -> Component + 'static,
+    >(
+        generator: impl FnOnce(StreamSender<T>) -> F,
         on_message: impl Fn(&mut Self, &Message) -> MessageFlow + 'static,
-        builder: impl Fn(&Stream<T, TaskRet>) -> Component + 'static,
+        builder: B,
     ) -> Component {
-        let (sender, receiver) = unbounded_channel();
-        Rc::new(RefCell::new(Widget {
-            state: Stream {
-                task: Task::Running(go(generator(sender))),
-                receiver,
-                current: None,
-            },
-            prev: None,
-            needs_rebuild: true,
-            builder: Box::new(builder),
-            id: uid(),
-            on_message: Rc::new(move |this, msg| {
-                switch(msg).case(|&Tick(_)| {
-                    if this.state.check() {
-                        this.set_state(|_| {});
-                    }
-                });
-                if let Propagate = on_message(this, msg)
-                    && let Some(prev) = &this.prev
-                {
-                    prev.borrow_mut().on_message(msg);
+        Self::stream_with(
+            StreamOptions::default(),
+            generator,
+            None,
+            |current, value| *current = Some(value),
+            on_message,
+            builder,
+        )
+    }
+}
+
+impl<T, TaskRet, Acc> Widget<Stream<T, TaskRet, Acc>>
+where
+    T: 'static + Send + Sync,
+    TaskRet: Send + Sync + 'static,
+    Acc: 'static,
+{
+    /// Folds every value `generator` sends into `init`, taking all that arrived once per frame
+    pub fn stream_with<
+        F: Future<Output = TaskRet> + Send + Sync + 'static,
+        B: Fn(&Stream<T, TaskRet, Acc>) -> Component + 'static,
+    >(
+        options: StreamOptions,
+        generator: impl FnOnce(StreamSender<T>) -> F,
+        init: Acc,
+        fold: impl Fn(&mut Acc, T) + 'static,
+        on_message: impl Fn(&mut Self, &Message) -> MessageFlow + 'static,
+        builder: B,
+    ) -> Component {
+        let stream = Stream::new(options, init, fold, |sender| go(generator(sender)));
+        Widget {
+            release: |stream: &mut Stream<T, TaskRet, Acc>| {
+                if let Task::Running(task) = &stream.task {
+                    task.abort();
                 }
-            }),
-            create_element: Rc::new(create_child),
-        }))
+            },
+            ..Self::create(
+                stream,
+                TypeId::of::<B>(),
+                Box::new(builder),
+                Rc::new(move |this, msg| {
+                    switch(msg).case(|_: &Poll| {
+                        if this.state.check() {
+                            this.set_state(|_| {});
+                        }
+                    });
+                    on_message(this, msg)
+                }),
+                Rc::new(create_child),
+                no_children,
+            )
+        }
+        .component()
     }
 }
 
-impl<State> _Component for Widget<State> {
+impl<State> Widget<State> {
+    /// Runs the `use_message` hooks and then `on_message`, unless a hook intercepted `msg`
+    fn receive(&mut self, msg: &Message) -> MessageFlow {
+        match self.hooks.on_message(msg) {
+            Intercept => Intercept,
+            Propagate => (self.on_message.clone())(self, msg),
+        }
+    }
+    /// The built child and the components in the state, which messages are passed on to
+    fn recipients(&mut self) -> Vec<Component> {
+        let mut recipients = self.prev.iter().cloned().collect::<Vec<_>>();
+        recipients.extend(
+            (self.children)(&mut self.state)
+                .into_iter()
+                .map(|c| c.clone()),
+        );
+        recipients
+    }
+}
+
+impl<State: 'static> _Component for Widget<State> {
     #[inline]
     fn id(&self) -> usize {
         self.id
     }
-    #[inline]
     fn create_element(&mut self) -> (bool, Box<dyn Element>) {
-        (self.create_element.clone())(self)
+        if !self.mounted {
+            self.on_mount();
+        }
+        tree::enter(self.id);
+        if self.focusable {
+            focus::register(self.id);
+        }
+        if let Some(keymap) = &self.keymap {
+            keymap::register(self.id, keymap.clone());
+        }
+        if let Some(interval) = self.tick_interval {
+            tick::register(self.id, interval);
+        }
+        let (did_rebuild, child) = (self.create_element.clone())(self);
+        tree::exit();
+        (did_rebuild, Box::new(RegionElement { id: self.id, child }))
     }
     #[inline]
     fn on_message(&mut self, event: &Message) {
-        (self.on_message.clone())(self, event);
+        let is_key = event.is::<KeyEvent>() || event.is::<Paste>() || event.is::<Action>();
+        if is_key && !focus::receives_keys(self.id) {
+            return;
+        }
+        let flow = match event.downcast_ref::<Mouse>() {
+            Some(mouse) => match mouse.relative_to(self.id) {
+                Some(mouse) => self.receive(&any(mouse)),
+                None => return,
+            },
+            None => self.receive(event),
+        };
+        if let Propagate = flow {
+            for child in self.recipients() {
+                child.borrow_mut().on_message(event);
+            }
+        }
+    }
+    fn deliver(&mut self, path: &[usize], msg: &Message, bubble: bool) -> MessageFlow {
+        if let Some((&next, rest)) = path.split_first() {
+            let Some(child) = self
+                .recipients()
+                .into_iter()
+                .find(|child| child.borrow().id() == next)
+            else {
+                return Intercept;
+            };
+            let flow = child.borrow_mut().deliver(rest, msg, bubble);
+            if !bubble || matches!(flow, Intercept) {
+                return Intercept;
+            }
+        }
+        self.receive(msg)
+    }
+    #[inline]
+    fn set_focusable(&mut self, focusable: bool) {
+        self.focusable = focusable;
+    }
+    #[inline]
+    fn set_tick_interval(&mut self, interval: Option<Duration>) {
+        self.tick_interval = interval;
+    }
+    #[inline]
+    fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = Some(Rc::new(keymap));
+    }
+    #[inline]
+    fn key(&self) -> Option<u64> {
+        self.key
+    }
+    #[inline]
+    fn set_key(&mut self, key: u64) {
+        self.key = Some(key);
+    }
+    #[inline]
+    fn kind(&self) -> TypeId {
+        self.kind
+    }
+    fn children(&mut self) -> Vec<&mut Component> {
+        (self.children)(&mut self.state)
+    }
+    fn attached(&mut self) -> Vec<Component> {
+        self.recipients()
+    }
+    fn on_mount(&mut self) {
+        self.mounted = true;
+        (self.start)(&mut self.state);
+        self.on_mount.iter_mut().for_each(|f| f());
+    }
+    fn on_unmount(&mut self) {
+        if !self.mounted {
+            return;
+        }
+        self.mounted = false;
+        (self.release)(&mut self.state);
+        self.hooks.clear();
+        self.on_unmount.iter_mut().for_each(|f| f());
+    }
+    fn add_mount_callback(&mut self, f: Box<dyn FnMut()>) {
+        self.on_mount.push(f);
+    }
+    fn add_unmount_callback(&mut self, f: Box<dyn FnMut()>) {
+        self.on_unmount.push(f);
+    }
+    fn adopt(&mut self, new: &mut dyn _Component) {
+        let new = new
+            .as_any()
+            .downcast_mut::<Self>()
+            .expect("Only components of the same kind are adopted");
+        (new.merge)(&mut self.state, &mut new.state);
+        std::mem::swap(&mut self.builder, &mut new.builder);
+        self.on_message = new.on_message.clone();
+        self.create_element = new.create_element.clone();
+        self.focusable = new.focusable;
+        self.keymap = new.keymap.clone();
+        self.merge = new.merge;
+        // Mounting is over, but unmounting runs what the latest build asked for
+        self.on_mount = std::mem::take(&mut new.on_mount);
+        self.on_unmount = std::mem::take(&mut new.on_unmount);
+        // The tick interval stays as mounted, since `animate` turns its own off once done
+        self.needs_rebuild = true;
+    }
+    fn as_any(&mut self) -> &mut dyn Any {
+        self
     }
 }
 
@@ -1,32 +1,40 @@
-use std::f64::consts::PI;
+use std::time::Duration;
 
 use crossterm::event::{KeyCode, KeyEvent};
 use stdext::prelude::*;
 
 use crate::{
+    animation::{Animation, Easing, FRAME, Repeat},
     message::prelude::*,
     prelude::{Component, column, text},
-    render::Tick,
+    tick::{Tick, ticking},
     widget::prelude::*,
 };
 
+/// A ball swinging from side to side, which space sends back to the start
 pub fn animated_char() -> Component {
-    Widget::stateful(
-        0,
-        |this, msg| {
-            switch(msg)
-                .case::<Tick>(|_| this.set_state(|num_ticks| *num_ticks += 1))
-                .case::<KeyEvent>(|event| match event.code {
-                    KeyCode::Char(' ') => this.set_state(|num_ticks| *num_ticks = 0),
-                    _ => {}
-                });
-            Propagate
-        },
-        |&num_ticks| {
-            let c = ((num_ticks as f64 - 90.0) * PI / 180.0).sin();
-            let index = std::cmp::max(0, std::cmp::min((c * 10.0 + 10.0).round() as i64, 19));
-            let s = (0..20).map(|i| if i == index { "⚪️" } else { " " });
-            column([text(s.collect::<String>()), text(index.to_string())])
-        },
+    ticking(
+        Widget::stateful(
+            Animation::new(0usize, 19, Duration::from_millis(1800))
+                .easing(Easing::EaseInOut)
+                .repeat(Repeat::Forever)
+                .reverse(),
+            |this, msg| {
+                switch(msg)
+                    .case(|&Tick(now)| this.set_state(|animation| animation.advance(now)))
+                    .case::<KeyEvent>(|event| {
+                        if event.code == KeyCode::Char(' ') {
+                            this.set_state(Animation::restart)
+                        }
+                    });
+                Propagate
+            },
+            |animation| {
+                let index = animation.value();
+                let s = (0..20).map(|i| if i == index { "⚪️" } else { " " });
+                column([text(s.collect::<String>()), text(index.to_string())])
+            },
+        ),
+        FRAME,
     )
 }
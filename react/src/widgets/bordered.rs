@@ -0,0 +1,96 @@
+use std::fmt::Display;
+
+use crate::{
+    component::prelude::*,
+    message::prelude::*,
+    prelude::{BorderElement, Lines, Style},
+    widget::Widget,
+};
+
+/// How `bordered_with` draws its border
+#[derive(Debug, Clone, Default)]
+pub struct Border {
+    pub lines: Lines,
+    pub title: Option<String>,
+    pub style: Style,
+}
+
+impl Border {
+    pub fn lines(self, lines: Lines) -> Self {
+        Self { lines, ..self }
+    }
+    pub fn title(self, title: impl Display) -> Self {
+        Self {
+            title: Some(title.to_string()),
+            ..self
+        }
+    }
+    pub fn style(self, style: Style) -> Self {
+        Self { style, ..self }
+    }
+}
+
+/// Surrounds `child` with a single line border
+pub fn bordered(child: Component) -> Component {
+    bordered_with(Border::default(), child)
+}
+
+pub fn bordered_with(border: Border, child: Component) -> Component {
+    Widget::container(
+        (border, child),
+        |(_, child)| vec![child],
+        |_, _| Propagate,
+        |this| {
+            let (border, child) = &this.state;
+            let (did_rebuild, child) = child.borrow_mut().create_element();
+            (
+                did_rebuild,
+                Box::new(BorderElement {
+                    lines: border.lines,
+                    title: border.title.clone(),
+                    style: border.style,
+                    child,
+                }),
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::{Harness, Point, Size, row, text, text_field};
+
+    #[test]
+    fn draws_a_box_around_the_child() {
+        let harness = Harness::mount(bordered(text("hi")), Size { x: 4, y: 3 });
+        harness.lines().must_be(vec!["┌──┐", "│hi│", "└──┘"]);
+    }
+
+    #[test]
+    fn titles_sit_in_the_top_edge() {
+        let border = Border::default().lines(Lines::Rounded).title("Tasks");
+        let harness = Harness::mount(bordered_with(border, text("a")), Size { x: 10, y: 3 });
+        harness
+            .lines()
+            .must_be(vec!["╭ Tasks ─╮", "│a       │", "╰────────╯"]);
+        let border = Border::default().lines(Lines::Double).title("Tasks");
+        let harness = Harness::mount(bordered_with(border, text("a")), Size { x: 7, y: 3 });
+        harness.lines()[0].clone().must_be("╔ Ta… ╗");
+    }
+
+    #[test]
+    fn panes_side_by_side() {
+        let mut harness = Harness::mount(
+            row([bordered(text_field("").0), bordered(text_field("").0)]),
+            Size { x: 8, y: 3 },
+        );
+        harness.type_str("a");
+        harness
+            .lines()
+            .must_be(vec!["┌──┐┌──┐", "│a ││  │", "└──┘└──┘"]);
+        harness.cursor().must_be(Some(Point { x: 2, y: 1 }));
+    }
+}
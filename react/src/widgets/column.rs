@@ -1,19 +1,16 @@
-use crate::{component::prelude::*, elements::column_element::ColumnElement, widget::prelude::*};
+use crate::{
+    component::prelude::*,
+    elements::flex::{Axis, FlexLayout},
+    widgets::flex::{FlexChild, flex_box},
+};
 
-pub fn column(children: impl IntoIterator<Item = Component>) -> Component {
-    let widgets = children.into_iter().collect::<Vec<_>>();
-    Widget::elemental(widgets, propagate, |this| {
-        let (did_rebuild, children): (Vec<_>, Vec<_>) = this
-            .state
-            .iter()
-            .map(|child| child.borrow_mut().create_element())
-            .unzip();
-        let did_any_child_rebuild = did_rebuild.into_iter().fold(false, |acc, e| acc || e);
-        (
-            did_any_child_rebuild,
-            Box::new(ColumnElement {
-                children,
-            }),
-        )
-    })
+pub fn column(children: impl IntoIterator<Item = impl Into<FlexChild>>) -> Component {
+    column_with(FlexLayout::default(), children)
+}
+
+pub fn column_with(
+    layout: FlexLayout,
+    children: impl IntoIterator<Item = impl Into<FlexChild>>,
+) -> Component {
+    flex_box(Axis::Vertical, layout, children)
 }
@@ -18,3 +18,28 @@ pub fn counter(i: i32) -> Component {
         |state| number(*state),
     )
 }
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::{Harness, Size};
+
+    #[test]
+    fn plus_and_minus_change_the_count() {
+        let mut harness = Harness::mount(counter(3), Size { x: 5, y: 1 });
+        harness.text().must_be("3");
+        harness.press(KeyCode::Char('+')).press(KeyCode::Char('+'));
+        harness.text().must_be("5");
+        harness.press(KeyCode::Char('-'));
+        harness.text().must_be("4");
+    }
+
+    #[test]
+    fn other_keys_are_ignored() {
+        let mut harness = Harness::mount(counter(0), Size { x: 5, y: 1 });
+        harness.type_str("a1");
+        harness.text().must_be("0");
+    }
+}
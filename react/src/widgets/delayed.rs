@@ -16,7 +16,7 @@ pub fn delayed(secs: u64) -> Component {
             column([
                 match opt {
                     Task::Done(_) => text("After"),
-                    Task::Running(_) => text("Before"),
+                    Task::Pending(_) | Task::Running(_) => text("Before"),
                     Task::Err(_) => text("Error!"),
                 },
                 timer(),
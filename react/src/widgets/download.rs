@@ -1,23 +1,23 @@
-use std::{fmt::Display, sync::Arc};
+use std::fmt::Display;
 
-use crate::prelude::{Component, Propagate, Task, Widget, text};
+use crate::prelude::{Component, Resource, keyed, resource, text};
 
+/// Shows the start of the page at `url`, or why it could not be fetched.
+/// Rebuilt with another `url`, it downloads that one instead.
 pub fn download(url: impl Display + 'static) -> Component {
-    let url = Arc::new(url.to_string());
-    Widget::future(
-        async move {
-            reqwest::get(url.to_string())
-                .await
-                .unwrap()
-                .text()
-                .await
-                .unwrap()
+    let url = url.to_string();
+    let fetched = url.clone();
+    let page = resource(
+        move || {
+            let url = fetched.clone();
+            async move { reqwest::get(url).await?.error_for_status()?.text().await }
         },
-        |_, _| Propagate,
         |content| match content {
-            Task::Done(s) => text(s[0..100].to_string()),
-            Task::Running(_) => text("Downloading"),
-            Task::Err(_) => text("Error!"),
+            Resource::Ok(s) => text(s.chars().take(100).collect::<String>()),
+            Resource::Loading => text("Downloading"),
+            Resource::Err(err) => text(format!("Error: {err}")),
+            Resource::Failed(_) => text("Error!"),
         },
-    )
+    );
+    keyed(url, page)
 }
@@ -0,0 +1,101 @@
+use crate::{
+    component::prelude::*,
+    elements::flex::{Axis, Flex, FlexItem, FlexLayout},
+    message::prelude::*,
+    prelude::{ColumnElement, RowElement},
+    widget::prelude::*,
+};
+
+/// A child of a row or column together with how it should be sized
+pub struct FlexChild {
+    pub flex: Flex,
+    pub component: Component,
+}
+
+impl From<Component> for FlexChild {
+    fn from(component: Component) -> Self {
+        Self {
+            flex: Flex::default(),
+            component,
+        }
+    }
+}
+
+impl FlexChild {
+    pub fn min(self, min: isize) -> Self {
+        Self {
+            flex: self.flex.min(min),
+            ..self
+        }
+    }
+    pub fn max(self, max: isize) -> Self {
+        Self {
+            flex: self.flex.max(max),
+            ..self
+        }
+    }
+}
+
+/// Takes a share of the free space proportional to `factor`
+pub fn flex(factor: usize, child: Component) -> FlexChild {
+    FlexChild {
+        flex: Flex::grow(factor),
+        component: child,
+    }
+}
+
+/// Takes exactly `size` cells along the main axis
+pub fn fixed(size: isize, child: Component) -> FlexChild {
+    FlexChild {
+        flex: Flex::fixed(size),
+        component: child,
+    }
+}
+
+/// Takes as much space as its content needs
+pub fn fit(child: Component) -> FlexChild {
+    FlexChild {
+        flex: Flex::fit(),
+        component: child,
+    }
+}
+
+pub(crate) fn flex_box(
+    axis: Axis,
+    layout: FlexLayout,
+    children: impl IntoIterator<Item = impl Into<FlexChild>>,
+) -> Component {
+    let children = children.into_iter().map(Into::into).collect::<Vec<_>>();
+    Widget::container(
+        children,
+        |children| {
+            children
+                .iter_mut()
+                .map(|child| &mut child.component)
+                .collect()
+        },
+        |_, _| Propagate,
+        move |this| {
+            let mut did_any_child_rebuild = false;
+            let children = this
+                .state
+                .iter()
+                .map(|child| {
+                    let (did_rebuild, element) = child.component.borrow_mut().create_element();
+                    did_any_child_rebuild |= did_rebuild;
+                    FlexItem {
+                        flex: child.flex,
+                        element,
+                    }
+                })
+                .collect();
+            (
+                did_any_child_rebuild,
+                match axis {
+                    Axis::Horizontal => Box::new(RowElement { layout, children }),
+                    Axis::Vertical => Box::new(ColumnElement { layout, children }),
+                },
+            )
+        },
+    )
+}
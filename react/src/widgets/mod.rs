@@ -1,14 +1,22 @@
 pub mod animated_char;
+pub mod bordered;
 pub mod column;
 pub mod counter;
 pub mod delayed;
 pub mod download;
 pub mod fast_counter;
+pub mod flex;
 pub mod number;
+pub mod padding;
+pub mod resource;
 pub mod row;
+pub mod scroll_view;
+pub mod select_list;
 pub mod single_char;
 pub mod single_line;
 pub mod streamed_counter;
+pub mod styled;
+pub mod table;
 pub mod text;
 pub mod text_cursor;
 pub mod text_field;
@@ -16,9 +24,29 @@ pub mod timer;
 
 pub mod prelude {
     pub use super::{
-        animated_char::animated_char, column::column, counter::counter, delayed::delayed,
-        download::download, fast_counter::fast_counter, number::number, row::row,
-        single_char::single_char, single_line::single_line, text::text, text_cursor::text_cursor,
-        text_field::text_field, timer::timer,
+        animated_char::animated_char,
+        bordered::{Border, bordered, bordered_with},
+        column::{column, column_with},
+        counter::counter,
+        delayed::delayed,
+        download::download,
+        fast_counter::fast_counter,
+        flex::{FlexChild, fit, fixed, flex},
+        number::number,
+        padding::padding,
+        resource::{Refetch, Resource, ResourceOptions, Retry, resource, resource_with},
+        row::{row, row_with},
+        scroll_view::{ScrollIntoView, ScrollOptions, scroll_view, scroll_view_with},
+        select_list::{
+            Activated, Deselected, SelectOptions, Selected, select_list, select_list_with,
+        },
+        single_char::single_char,
+        single_line::single_line,
+        styled::styled,
+        table::{RowActivated, table},
+        text::text,
+        text_cursor::{text_cursor, text_selection},
+        text_field::{TextFieldOptions, text_field, text_field_with},
+        timer::timer,
     };
 }
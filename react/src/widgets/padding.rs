@@ -0,0 +1,57 @@
+use crate::{
+    component::prelude::*,
+    message::prelude::*,
+    prelude::{Insets, PaddingElement},
+    widget::Widget,
+};
+
+/// Keeps `insets` worth of empty space around `child`
+pub fn padding(child: Component, insets: Insets) -> Component {
+    Widget::container(
+        (insets, child),
+        |(_, child)| vec![child],
+        |_, _| Propagate,
+        |this| {
+            let (insets, child) = &this.state;
+            let (did_rebuild, child) = child.borrow_mut().create_element();
+            (
+                did_rebuild,
+                Box::new(PaddingElement {
+                    insets: *insets,
+                    child,
+                }),
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::{Harness, Size, bordered, column, fit, text};
+
+    #[test]
+    fn insets_shift_and_shrink_the_child() {
+        let harness = Harness::mount(
+            padding(text("abcd"), Insets::symmetric(1, 2)),
+            Size { x: 6, y: 4 },
+        );
+        harness.lines().must_be(vec!["", "  ab", "  cd", ""]);
+    }
+
+    #[test]
+    fn intrinsic_size_includes_the_insets() {
+        let harness = Harness::mount(
+            column([
+                fit(bordered(padding(text("x"), Insets::all(1)))),
+                fit(text("y")),
+            ]),
+            Size { x: 5, y: 6 },
+        );
+        harness
+            .lines()
+            .must_be(vec!["┌───┐", "│   │", "│ x │", "│   │", "└───┘", "y"]);
+    }
+}
@@ -0,0 +1,346 @@
+use std::{sync::Arc, time::Duration};
+
+use stdext::prelude::switch;
+use tokio::{task::JoinError, time::sleep};
+
+use crate::{
+    message::prelude::*,
+    prelude::{Component, Task, Widget},
+    runtime::go,
+};
+
+/// Makes a `resource` fetch again, e.g. when sent through its `Handle`. Broadcast with `send`,
+/// it reaches every resource on its way down, so they all fetch again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Refetch;
+
+/// How often to try again after a failed fetch, waiting `initial`, then twice as long every time
+/// up to `max`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retry {
+    pub attempts: u32,
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Retry {
+    pub const NEVER: Retry = Retry {
+        attempts: 0,
+        initial: Duration::ZERO,
+        max: Duration::ZERO,
+    };
+    /// How long to wait before retry number `retry`, counting from 0
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial
+            .checked_mul(2u32.saturating_pow(retry))
+            .unwrap_or(self.max)
+            .min(self.max)
+    }
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial: Duration::from_millis(250),
+            max: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceOptions {
+    pub retry: Retry,
+}
+
+/// What a `resource` has to show
+#[derive(Debug)]
+pub enum Resource<'a, T, E> {
+    Loading,
+    Ok(&'a T),
+    /// The error of the last attempt, once retries ran out
+    Err(&'a E),
+    /// The fetcher panicked
+    Failed(&'a JoinError),
+}
+
+impl<'a, T, E> From<&'a Task<Result<T, E>>> for Resource<'a, T, E> {
+    fn from(task: &'a Task<Result<T, E>>) -> Self {
+        match task {
+            Task::Pending(_) | Task::Running(_) => Resource::Loading,
+            Task::Done(Ok(value)) => Resource::Ok(value),
+            Task::Done(Err(err)) => Resource::Err(err),
+            Task::Err(err) => Resource::Failed(err),
+        }
+    }
+}
+
+/// Calls `fetcher` until it succeeds or `retry` gives up
+async fn fetch<T, E, F>(fetcher: Arc<impl Fn() -> F>, retry: Retry) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let mut retries = 0;
+    loop {
+        match fetcher().await {
+            Err(_) if retries < retry.attempts => {
+                sleep(retry.backoff(retries)).await;
+                retries += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+pub fn resource<T, E, F, B>(
+    fetcher: impl Fn() -> F + Send + Sync + 'static,
+    builder: B,
+) -> Component
+where
+    T: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+    F: Future<Output = Result<T, E>> + Send + Sync + 'static,
+    B: Fn(Resource<T, E>) -> Component + 'static,
+{
+    resource_with(ResourceOptions::default(), fetcher, builder)
+}
+
+/// Shows what `fetcher` produces once it is done, retrying failures as `options` says.
+/// Receiving `Refetch` starts over, cancelling a fetch still in progress. To refetch only this
+/// one, send it to its id rather than broadcasting it. The fetch starts once mounted and a
+/// rebuilt parent keeps it; `keyed` by what `fetcher` fetches, it starts over when that changes.
+pub fn resource_with<T, E, F, B>(
+    options: ResourceOptions,
+    fetcher: impl Fn() -> F + Send + Sync + 'static,
+    builder: B,
+) -> Component
+where
+    T: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+    F: Future<Output = Result<T, E>> + Send + Sync + 'static,
+    B: Fn(Resource<T, E>) -> Component + 'static,
+{
+    let fetcher = Arc::new(fetcher);
+    let retry = options.retry;
+    Widget::future(
+        fetch(fetcher.clone(), retry),
+        move |this, msg| {
+            switch(msg).case(|_: &Refetch| {
+                let task = go(fetch(fetcher.clone(), retry));
+                this.set_state(|state| *state = Task::Running(task));
+            });
+            Propagate
+        },
+        move |task| builder(Resource::from(task)),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::Instant,
+    };
+
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::{Harness, Size, column, download, text};
+
+    /// Serves `responses` in order, one per connection, and counts the requests
+    fn serve(responses: Vec<(u16, &'static str)>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counted = requests.clone();
+        thread::spawn(move || {
+            for ((status, body), stream) in responses.into_iter().zip(listener.incoming()) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                counted.fetch_add(1, Ordering::SeqCst);
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    async fn get(url: String) -> Result<String, String> {
+        let response = reqwest::get(url).await.map_err(|err| err.to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(status.to_string());
+        }
+        response.text().await.map_err(|err| err.to_string())
+    }
+
+    fn page(options: ResourceOptions, url: &str) -> Component {
+        let url = url.to_string();
+        resource_with(
+            options,
+            move || get(url.clone()),
+            |page| match page {
+                Resource::Loading => text("loading"),
+                Resource::Ok(body) => text(body.clone()),
+                Resource::Err(err) => text(format!("error {err}")),
+                Resource::Failed(_) => text("failed"),
+            },
+        )
+    }
+
+    fn quick(attempts: u32) -> ResourceOptions {
+        ResourceOptions {
+            retry: Retry {
+                attempts,
+                initial: Duration::from_millis(1),
+                max: Duration::from_millis(5),
+            },
+        }
+    }
+
+    /// Lets time pass until the first line reads `expected`
+    fn wait_for_line(harness: &mut Harness, expected: &str) -> String {
+        let start = Instant::now();
+        loop {
+            harness.tick(Duration::from_millis(5));
+            let line = harness.lines()[0].clone();
+            if line == expected || start.elapsed() > Duration::from_secs(5) {
+                return line;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Lets time pass until the lines read `expected`
+    fn wait_for_lines(harness: &mut Harness, expected: &[&str]) -> Vec<String> {
+        let start = Instant::now();
+        loop {
+            harness.tick(Duration::from_millis(5));
+            let lines = harness.lines();
+            if lines == expected || start.elapsed() > Duration::from_secs(5) {
+                return lines;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let retry = Retry {
+            attempts: 5,
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(300),
+        };
+        [0, 1, 2, 40]
+            .map(|retry_number| retry.backoff(retry_number).as_millis())
+            .must_be([100, 200, 300, 300]);
+    }
+
+    #[test]
+    fn failures_are_retried() {
+        let (url, requests) = serve(vec![(500, ""), (503, ""), (200, "fine")]);
+        let mut harness = Harness::mount(page(quick(2), &url), Size { x: 20, y: 1 });
+        harness.lines().must_be(vec!["loading"]);
+        wait_for_line(&mut harness, "fine").must_be("fine");
+        requests.load(Ordering::SeqCst).must_be(3);
+    }
+
+    #[test]
+    fn errors_show_once_retries_run_out() {
+        let (url, requests) = serve(vec![(404, ""), (404, "")]);
+        let mut harness = Harness::mount(page(quick(1), &url), Size { x: 20, y: 1 });
+        wait_for_line(&mut harness, "error 404 Not Found").must_be("error 404 Not Found");
+        requests.load(Ordering::SeqCst).must_be(2);
+    }
+
+    #[test]
+    fn panicking_fetchers_fail() {
+        async fn explode() -> Result<String, String> {
+            panic!("The fetcher broke")
+        }
+        let mut harness = Harness::mount(
+            resource_with(quick(0), explode, |page| match page {
+                Resource::Failed(_) => text("failed"),
+                _ => text("loading"),
+            }),
+            Size { x: 20, y: 1 },
+        );
+        wait_for_line(&mut harness, "failed").must_be("failed");
+    }
+
+    #[test]
+    fn refetch_starts_over() {
+        let (url, requests) = serve(vec![(200, "first"), (200, "second")]);
+        let mut harness = Harness::mount(page(quick(0), &url), Size { x: 20, y: 1 });
+        wait_for_line(&mut harness, "first").must_be("first");
+        harness.send(Refetch);
+        harness.lines().must_be(vec!["loading"]);
+        wait_for_line(&mut harness, "second").must_be("second");
+        requests.load(Ordering::SeqCst).must_be(2);
+    }
+
+    #[test]
+    fn broadcast_refetches_reach_every_resource() {
+        let (first, _) = serve(vec![(200, "a1"), (200, "a2"), (200, "a3")]);
+        let (second, requests) = serve(vec![(200, "b1"), (200, "b2")]);
+        let targeted = page(quick(0), &first);
+        let id = targeted.borrow().id();
+        let mut harness = Harness::mount(
+            column([targeted, page(quick(0), &second)]),
+            Size { x: 20, y: 2 },
+        );
+        wait_for_lines(&mut harness, &["a1", "b1"]).must_be(vec!["a1", "b1"]);
+        harness.send_to(id, Refetch);
+        harness.lines().must_be(vec!["loading", "b1"]);
+        wait_for_lines(&mut harness, &["a2", "b1"]).must_be(vec!["a2", "b1"]);
+        requests.load(Ordering::SeqCst).must_be(1);
+        harness.send(Refetch);
+        harness.lines().must_be(vec!["loading", "loading"]);
+        wait_for_lines(&mut harness, &["a3", "b2"]).must_be(vec!["a3", "b2"]);
+    }
+
+    #[test]
+    fn rebuilds_fetch_only_when_the_url_changes() {
+        // Room for more requests than expected, so that extra ones are counted
+        let (first, first_requests) = serve(vec![(200, "first"), (200, "again")]);
+        let first_url = first.clone();
+        let (second, second_requests) = serve(vec![(200, "second"), (200, "again")]);
+        let mut harness = Harness::mount(
+            Widget::stateful(
+                first,
+                |this, msg| {
+                    switch(msg)
+                        .case(|url: &String| this.set_state(|current| *current = url.clone()));
+                    Propagate
+                },
+                |url| download(url.clone()),
+            ),
+            Size { x: 20, y: 1 },
+        );
+        wait_for_line(&mut harness, "first").must_be("first");
+        harness.send(first_url.clone());
+        wait_for_line(&mut harness, "first").must_be("first");
+        harness.send(second);
+        wait_for_line(&mut harness, "second").must_be("second");
+        first_requests.load(Ordering::SeqCst).must_be(1);
+        second_requests.load(Ordering::SeqCst).must_be(1);
+    }
+
+    #[test]
+    fn downloads_show_short_bodies_whole() {
+        let (url, _) = serve(vec![(200, "héllo")]);
+        let mut harness = Harness::mount(download(url), Size { x: 20, y: 1 });
+        wait_for_line(&mut harness, "héllo").must_be("héllo");
+    }
+}
@@ -1,19 +1,16 @@
-use crate::{component::prelude::*, prelude::RowElement, widget::prelude::*};
+use crate::{
+    component::prelude::*,
+    elements::flex::{Axis, FlexLayout},
+    widgets::flex::{FlexChild, flex_box},
+};
 
-pub fn row(children: impl IntoIterator<Item = Component>) -> Component {
-    let widgets = children.into_iter().collect::<Vec<_>>();
-    Widget::elemental(widgets, propagate, move |this| {
-        let (did_rebuild, children): (Vec<_>, Vec<_>) = this
-            .state
-            .iter()
-            .map(|child| child.borrow_mut().create_element())
-            .unzip();
-        let did_any_child_rebuild = did_rebuild.into_iter().fold(false, |acc, e| acc || e);
-        (
-            did_any_child_rebuild,
-            Box::new(RowElement {
-                children,
-            }),
-        )
-    })
+pub fn row(children: impl IntoIterator<Item = impl Into<FlexChild>>) -> Component {
+    row_with(FlexLayout::default(), children)
+}
+
+pub fn row_with(
+    layout: FlexLayout,
+    children: impl IntoIterator<Item = impl Into<FlexChild>>,
+) -> Component {
+    flex_box(Axis::Horizontal, layout, children)
 }
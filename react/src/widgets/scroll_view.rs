@@ -0,0 +1,255 @@
+use std::sync::{Arc, Mutex};
+
+use crossterm::event::{KeyCode, KeyEvent, MouseEventKind};
+use stdext::prelude::*;
+
+use crate::{
+    component::prelude::*,
+    focus::{self, Focused},
+    message::prelude::*,
+    mouse::{self, Mouse},
+    prelude::{Point, ScrollElement, ScrollMetrics, on_reuse},
+    tree,
+    widget::Widget,
+};
+
+/// Asks every scroll view around the widget `id` to scroll so that its `row`,
+/// counted from the widget's top, is visible
+#[derive(Debug, Clone, Copy)]
+pub struct ScrollIntoView {
+    pub id: usize,
+    pub row: isize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScrollOptions {
+    /// Lay the child out at its full width and scroll sideways instead of wrapping it
+    pub horizontal: bool,
+}
+
+const WHEEL_STEP: isize = 3;
+
+struct ScrollView {
+    child: Component,
+    options: ScrollOptions,
+    offset: Point,
+    metrics: Arc<Mutex<ScrollMetrics>>,
+    dirty: bool,
+}
+
+impl ScrollView {
+    /// Takes the child of a rebuilt view, keeping where it was scrolled to
+    fn reuse(&mut self, fresh: &mut ScrollView) {
+        std::mem::swap(&mut self.child, &mut fresh.child);
+        self.options = fresh.options;
+        self.dirty = true;
+    }
+    fn metrics(&self) -> ScrollMetrics {
+        *self.metrics.lock().expect("Scroll metrics poisoned")
+    }
+    fn scroll_to(&mut self, offset: Point) {
+        let offset = self.metrics().clamp(offset);
+        if offset != self.offset {
+            self.offset = offset;
+            self.dirty = true;
+        }
+    }
+    fn scroll_by(&mut self, delta: Point) {
+        self.scroll_to(self.metrics().offset + delta);
+    }
+    fn key(&mut self, code: KeyCode) {
+        let metrics = self.metrics();
+        let page = (metrics.viewport.y - 1).max(1);
+        let down = |y| Point { x: 0, y };
+        let right = |x| Point { x, y: 0 };
+        match code {
+            KeyCode::Up => self.scroll_by(down(-1)),
+            KeyCode::Down => self.scroll_by(down(1)),
+            KeyCode::PageUp => self.scroll_by(down(-page)),
+            KeyCode::PageDown => self.scroll_by(down(page)),
+            KeyCode::Home => self.scroll_to(Point::default()),
+            KeyCode::End => self.scroll_to(down(metrics.content.y)),
+            KeyCode::Left if self.options.horizontal => self.scroll_by(right(-1)),
+            KeyCode::Right if self.options.horizontal => self.scroll_by(right(1)),
+            _ => {}
+        }
+    }
+    /// Scrolls the least amount that brings `row` of the content into view
+    fn reveal(&mut self, row: isize) {
+        let metrics = self.metrics();
+        let top = metrics.offset.y;
+        if row < top {
+            self.scroll_to(Point {
+                y: row,
+                ..metrics.offset
+            });
+        } else if row >= top + metrics.viewport.y {
+            self.scroll_to(Point {
+                y: row - metrics.viewport.y + 1,
+                ..metrics.offset
+            });
+        }
+    }
+}
+
+pub fn scroll_view(child: Component) -> Component {
+    scroll_view_with(ScrollOptions::default(), child)
+}
+
+/// Shows a window onto `child` that scrolls with the arrow keys, PageUp/PageDown, Home/End and
+/// the mouse wheel. Keys are left alone while a widget inside the view has focus.
+pub fn scroll_view_with(options: ScrollOptions, child: Component) -> Component {
+    let view = Widget::container(
+        ScrollView {
+            child,
+            options,
+            offset: Point::default(),
+            metrics: Arc::default(),
+            dirty: false,
+        },
+        |state| vec![&mut state.child],
+        |this, msg| {
+            let id = this.id();
+            let reveal = |this: &mut Widget<ScrollView>, target: usize, row: isize| {
+                if target == id || !tree::is_ancestor(id, target) {
+                    return;
+                }
+                if let (Some(view), Some(rect)) = (mouse::region(id), mouse::region(target)) {
+                    let top = this.state.metrics().offset.y;
+                    this.state.reveal(rect.origin.y - view.origin.y + top + row);
+                }
+            };
+            switch(msg)
+                .case(|event: &KeyEvent| {
+                    let inside = focus::focused()
+                        .is_some_and(|focused| focused != id && tree::is_ancestor(id, focused));
+                    if !inside {
+                        this.state.key(event.code);
+                    }
+                })
+                .case(|event: &Mouse| match event.kind {
+                    MouseEventKind::ScrollUp => this.state.scroll_by(Point {
+                        x: 0,
+                        y: -WHEEL_STEP,
+                    }),
+                    MouseEventKind::ScrollDown => this.state.scroll_by(Point {
+                        x: 0,
+                        y: WHEEL_STEP,
+                    }),
+                    MouseEventKind::ScrollLeft => this.state.scroll_by(Point {
+                        x: -WHEEL_STEP,
+                        y: 0,
+                    }),
+                    MouseEventKind::ScrollRight => this.state.scroll_by(Point {
+                        x: WHEEL_STEP,
+                        y: 0,
+                    }),
+                    _ => {}
+                })
+                .case(|&Focused(focused)| reveal(this, focused, 0))
+                .case(|&ScrollIntoView { id, row }| reveal(this, id, row));
+            Propagate
+        },
+        |this| {
+            let (did_rebuild, child) = this.state.child.borrow_mut().create_element();
+            let dirty = std::mem::take(&mut this.state.dirty);
+            (
+                did_rebuild || dirty,
+                Box::new(ScrollElement {
+                    child,
+                    offset: this.state.offset,
+                    horizontal: this.state.options.horizontal,
+                    metrics: this.state.metrics.clone(),
+                }),
+            )
+        },
+    );
+    on_reuse(view, ScrollView::reuse)
+}
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::{Harness, Size, column, fixed, text, text_field};
+
+    fn numbers(count: usize) -> Component {
+        column((0..count).map(text))
+    }
+
+    #[test]
+    fn shows_a_window_with_a_scrollbar() {
+        let harness = Harness::mount(scroll_view(numbers(6)), Size { x: 3, y: 3 });
+        harness.lines().must_be(vec!["0 █", "1 │", "2 │"]);
+    }
+
+    #[test]
+    fn short_content_has_no_scrollbar() {
+        let harness = Harness::mount(scroll_view(numbers(2)), Size { x: 3, y: 3 });
+        harness.lines().must_be(vec!["0", "", "1"]);
+    }
+
+    #[test]
+    fn keys_scroll_within_bounds() {
+        let mut harness = Harness::mount(scroll_view(numbers(6)), Size { x: 3, y: 3 });
+        harness.press(KeyCode::Down);
+        harness.lines().must_be(vec!["1 █", "2 │", "3 │"]);
+        harness.press(KeyCode::PageDown).press(KeyCode::PageDown);
+        harness.lines().must_be(vec!["3 │", "4 │", "5 █"]);
+        harness.press(KeyCode::Home);
+        harness.lines()[0].clone().must_be("0 █");
+        harness.press(KeyCode::Up);
+        harness.lines()[0].clone().must_be("0 █");
+    }
+
+    #[test]
+    fn wheel_scrolls() {
+        let mut harness = Harness::mount(scroll_view(numbers(10)), Size { x: 3, y: 3 });
+        harness.mouse(MouseEventKind::ScrollDown, Point { x: 0, y: 1 });
+        harness.lines()[0].clone().must_be("3 █");
+    }
+
+    #[test]
+    fn scrolls_sideways() {
+        let options = ScrollOptions { horizontal: true };
+        let mut harness = Harness::mount(
+            scroll_view_with(options, text("abcdef")),
+            Size { x: 3, y: 2 },
+        );
+        harness.lines().must_be(vec!["abc", "█──"]);
+        harness.press(KeyCode::Right).press(KeyCode::Right);
+        harness.lines().must_be(vec!["cde", "─█─"]);
+    }
+
+    #[test]
+    fn focused_fields_are_scrolled_into_view() {
+        let mut harness = Harness::mount(
+            scroll_view(column(
+                (0..4)
+                    .map(|i| fixed(1, text(i)))
+                    .chain([fixed(1, text_field("").0), fixed(1, text_field("").0)]),
+            )),
+            Size { x: 4, y: 3 },
+        );
+        harness.lines()[0].clone().must_be("0  █");
+        harness.press(KeyCode::Tab).type_str("x");
+        harness.lines().must_be(vec!["3  │", "   │", "x  █"]);
+        harness.press(KeyCode::Tab);
+        harness.lines()[0].clone().must_be("3  │");
+    }
+
+    #[test]
+    fn scroll_into_view_by_id() {
+        let last = text("last");
+        let id = last.borrow().id();
+        let mut harness = Harness::mount(
+            scroll_view(column((0..5).map(text).chain([last]))),
+            Size { x: 5, y: 2 },
+        );
+        harness.send(ScrollIntoView { id, row: 0 });
+        harness.lines().must_be(vec!["4   │", "last█"]);
+        harness.send(ScrollIntoView { id, row: -5 });
+        harness.lines()[0].clone().must_be("0   █");
+    }
+}
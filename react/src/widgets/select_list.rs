@@ -0,0 +1,339 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEventKind};
+use stdext::prelude::*;
+
+use crate::{
+    component::prelude::*,
+    focus::prelude::*,
+    message::prelude::*,
+    mouse::Mouse,
+    prelude::{Style, column, fit, on_reuse, styled},
+    widget::prelude::*,
+    widgets::scroll_view::ScrollIntoView,
+};
+
+/// Bubbled up from the list with the id `list` when the item at `index` becomes selected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selected {
+    pub list: usize,
+    pub index: usize,
+}
+/// Bubbled up from a multi-select list when the item at `index` stops being selected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deselected {
+    pub list: usize,
+    pub index: usize,
+}
+/// Bubbled up from a list when Enter is pressed on the item at `index`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Activated {
+    pub list: usize,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SelectOptions {
+    /// Space toggles items instead of moving a single selection around
+    pub multi: bool,
+}
+
+struct SelectList {
+    items: Vec<String>,
+    options: SelectOptions,
+    highlighted: usize,
+    selected: BTreeSet<usize>,
+    focused: bool,
+}
+
+impl SelectList {
+    fn highlight(&mut self, index: usize) {
+        self.highlighted = index.min(self.items.len().saturating_sub(1));
+    }
+    /// Takes the items of a rebuilt list. The highlight and selection stay on the same items,
+    /// found by their text, and selected items that are gone are no longer selected.
+    fn reuse(&mut self, fresh: &mut SelectList) {
+        let old = std::mem::replace(&mut self.items, std::mem::take(&mut fresh.items));
+        self.options = fresh.options;
+        if old != self.items {
+            let moved = |index: usize| self.items.iter().position(|item| *item == old[index]);
+            let selected = self
+                .selected
+                .iter()
+                .filter_map(|&index| moved(index))
+                .collect();
+            let highlighted = moved(self.highlighted).unwrap_or(self.highlighted);
+            self.selected = selected;
+            self.highlighted = highlighted;
+        }
+        self.highlight(self.highlighted);
+    }
+    fn select(&mut self, list: usize) {
+        if self.items.is_empty() {
+            return;
+        }
+        let index = self.highlighted;
+        if !self.options.multi {
+            if self.selected.contains(&index) {
+                return;
+            }
+            self.selected.clear();
+        } else if self.selected.remove(&index) {
+            bubble(list, Deselected { list, index });
+            return;
+        }
+        self.selected.insert(index);
+        bubble(list, Selected { list, index });
+    }
+    fn row(&self, index: usize) -> (String, Style) {
+        let item = &self.items[index];
+        let is_selected = self.selected.contains(&index);
+        let marker = if index == self.highlighted {
+            "›"
+        } else {
+            " "
+        };
+        let s = match (self.options.multi, is_selected) {
+            (true, true) => format!("{marker} [x] {item}"),
+            (true, false) => format!("{marker} [ ] {item}"),
+            (false, _) => format!("{marker} {item}"),
+        };
+        let mut style = Style::default();
+        if is_selected && !self.options.multi {
+            style = style.bold();
+        }
+        if index == self.highlighted && self.focused {
+            style = style.reverse();
+        }
+        (s, style)
+    }
+}
+
+pub fn select_list(items: impl IntoIterator<Item = impl Display>) -> Component {
+    select_list_with(SelectOptions::default(), items)
+}
+
+/// A focusable list of items. Up/Down/Home/End and clicks move the highlight, Space selects
+/// and Enter activates, which parents learn about through `Selected`, `Deselected` and `Activated`
+/// bubbling up from the list.
+pub fn select_list_with(
+    options: SelectOptions,
+    items: impl IntoIterator<Item = impl Display>,
+) -> Component {
+    let list = Widget::stateful(
+        SelectList {
+            items: items.into_iter().map(|item| item.to_string()).collect(),
+            options,
+            highlighted: 0,
+            selected: BTreeSet::new(),
+            focused: false,
+        },
+        |this, msg| {
+            let id = this.id();
+            let highlighted = this.state.highlighted;
+            switch(msg)
+                .case(|event: &KeyEvent| match event.code {
+                    KeyCode::Up => {
+                        this.set_state(|list| list.highlight(highlighted.saturating_sub(1)))
+                    }
+                    KeyCode::Down => this.set_state(|list| list.highlight(highlighted + 1)),
+                    KeyCode::Home => this.set_state(|list| list.highlight(0)),
+                    KeyCode::End => this.set_state(|list| list.highlight(usize::MAX)),
+                    KeyCode::Char(' ') => this.set_state(|list| list.select(id)),
+                    KeyCode::Enter if !this.state.items.is_empty() => bubble(
+                        id,
+                        Activated {
+                            list: id,
+                            index: highlighted,
+                        },
+                    ),
+                    _ => {}
+                })
+                .case(|event: &Mouse| {
+                    if event.kind == MouseEventKind::Down(MouseButton::Left)
+                        && (0..this.state.items.len() as isize).contains(&event.position.y)
+                    {
+                        this.set_state(|list| {
+                            list.highlight(event.position.y as usize);
+                            list.select(id);
+                        });
+                    }
+                })
+                .case(|&Focused(focused)| {
+                    if focused == id {
+                        this.set_state(|list| list.focused = true)
+                    }
+                })
+                .case(|&Blurred(blurred)| {
+                    if blurred == id {
+                        this.set_state(|list| list.focused = false)
+                    }
+                });
+            if this.state.highlighted != highlighted {
+                send(ScrollIntoView {
+                    id,
+                    row: this.state.highlighted as isize,
+                });
+            }
+            // Lets what the list bubbles up pass on to its parent
+            Propagate
+        },
+        |list| {
+            column((0..list.items.len()).map(|index| {
+                let (s, style) = list.row(index);
+                fit(styled(s, style))
+            }))
+        },
+    );
+    focusable(on_reuse(list, SelectList::reuse))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::{Harness, Point, Size};
+
+    /// Wraps `list` in a parent recording the messages it sends
+    fn recorded(list: Component) -> (Harness, Rc<RefCell<Vec<String>>>) {
+        let log = Rc::new(RefCell::new(vec![]));
+        let parent = Widget::stateful(
+            log.clone(),
+            |this, msg| {
+                let mut log = this.state.borrow_mut();
+                switch(msg)
+                    .case(|&Selected { index, .. }| log.push(format!("selected {index}")))
+                    .case(|&Deselected { index, .. }| log.push(format!("deselected {index}")))
+                    .case(|&Activated { index, .. }| log.push(format!("activated {index}")));
+                Propagate
+            },
+            move |_| list.clone(),
+        );
+        (Harness::mount(parent, Size { x: 10, y: 3 }), log)
+    }
+
+    #[test]
+    fn arrows_move_the_highlight_within_bounds() {
+        let (mut harness, _) = recorded(select_list(["a", "b", "c"]));
+        harness.lines().must_be(vec!["› a", "  b", "  c"]);
+        harness
+            .press(KeyCode::Down)
+            .press(KeyCode::Down)
+            .press(KeyCode::Down);
+        harness.lines().must_be(vec!["  a", "  b", "› c"]);
+        harness.press(KeyCode::Home).press(KeyCode::Up);
+        harness.lines()[0].clone().must_be("› a");
+        harness.press(KeyCode::End);
+        harness.lines()[2].clone().must_be("› c");
+    }
+
+    #[test]
+    fn single_selection_moves() {
+        let (mut harness, log) = recorded(select_list(["a", "b"]));
+        harness
+            .press(KeyCode::Char(' '))
+            .press(KeyCode::Down)
+            .press(KeyCode::Char(' '))
+            .press(KeyCode::Enter);
+        log.borrow()
+            .clone()
+            .must_be(vec!["selected 0", "selected 1", "activated 1"]);
+        harness
+            .cell(Point { x: 2, y: 1 })
+            .and_then(|token| token.style())
+            .must_be(Some(Style::default().bold().reverse()));
+    }
+
+    #[test]
+    fn multi_selection_toggles() {
+        let options = SelectOptions { multi: true };
+        let (mut harness, log) = recorded(select_list_with(options, ["a", "b"]));
+        harness
+            .press(KeyCode::Char(' '))
+            .press(KeyCode::Down)
+            .press(KeyCode::Char(' '));
+        harness.lines().must_be(vec!["  [x] a", "› [x] b", ""]);
+        harness.press(KeyCode::Up).press(KeyCode::Char(' '));
+        harness.lines().must_be(vec!["› [ ] a", "  [x] b", ""]);
+        log.borrow()
+            .clone()
+            .must_be(vec!["selected 0", "selected 1", "deselected 0"]);
+    }
+
+    #[test]
+    fn clicking_selects_a_row() {
+        let (mut harness, log) = recorded(select_list(["a", "b", "c"]));
+        harness.click(Point { x: 4, y: 2 });
+        harness.lines()[2].clone().must_be("› c");
+        log.borrow().clone().must_be(vec!["selected 2"]);
+    }
+
+    #[test]
+    fn rebuilt_lists_keep_their_highlight_and_selection() {
+        let parent = Widget::stateful(
+            vec!["a", "b", "c"],
+            |this, msg| {
+                switch(msg).case(|_: &Activated| this.set_state(|items| items.push("d")));
+                Propagate
+            },
+            |items| select_list(items.clone()),
+        );
+        let mut harness = Harness::mount(parent, Size { x: 5, y: 4 });
+        harness
+            .press(KeyCode::Down)
+            .press(KeyCode::Char(' '))
+            .press(KeyCode::Enter);
+        harness.lines().must_be(vec!["  a", "› b", "  c", "  d"]);
+        harness
+            .cell(Point { x: 2, y: 1 })
+            .and_then(|token| token.style())
+            .must_be(Some(Style::default().bold().reverse()));
+    }
+
+    #[test]
+    fn rebuilt_lists_keep_selecting_the_same_items() {
+        let parent = Widget::stateful(
+            vec!["a", "b", "c"],
+            |this, msg| {
+                switch(msg).case(|_: &Activated| this.set_state(|items| _ = items.remove(0)));
+                Propagate
+            },
+            |items| select_list(items.clone()),
+        );
+        let mut harness = Harness::mount(parent, Size { x: 5, y: 3 });
+        harness
+            .press(KeyCode::End)
+            .press(KeyCode::Char(' '))
+            .press(KeyCode::Enter);
+        harness.lines().must_be(vec!["  b", "› c", ""]);
+        harness
+            .cell(Point { x: 2, y: 1 })
+            .and_then(|token| token.style())
+            .must_be(Some(Style::default().bold().reverse()));
+    }
+
+    #[test]
+    fn messages_say_which_list_sent_them() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let recorder = log.clone();
+        let (first, second) = (select_list(["a"]), select_list(["b"]));
+        let ids = [first.borrow().id(), second.borrow().id()];
+        let parent = Widget::stateful(
+            (),
+            move |_, msg| {
+                switch(msg).case(|&Activated { list, .. }| recorder.borrow_mut().push(list));
+                Propagate
+            },
+            move |_| column([first.clone(), second.clone()]),
+        );
+        let mut harness = Harness::mount(parent, Size { x: 5, y: 2 });
+        harness
+            .press(KeyCode::Enter)
+            .press(KeyCode::Tab)
+            .press(KeyCode::Enter);
+        log.borrow().clone().must_be(ids.to_vec());
+    }
+}
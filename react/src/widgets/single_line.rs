@@ -1,5 +1,5 @@
 use crate::{
-    prelude::{Component, StringElement},
+    prelude::{Component, StringElement, Style},
     widget::Widget,
 };
 
@@ -14,6 +14,8 @@ pub fn single_line(s: String) -> Component {
                 Box::new(StringElement {
                     s: this.state.clone(),
                     cursor: None,
+                    selection: None,
+                    style: Style::default(),
                 }),
             )
         },
@@ -10,7 +10,9 @@ pub fn streamed_counter() -> Component {
     Widget::stream(
         |sender| async move {
             for i in 0.. {
-                _ = sender.send(i);
+                if sender.send(i).await.is_err() {
+                    break;
+                }
                 tokio::time::sleep(Duration::from_secs(1)).await;
             }
         },
@@ -0,0 +1,25 @@
+use std::fmt::Display;
+
+use crate::{
+    component::prelude::*,
+    prelude::{StringElement, Style},
+    widget::Widget,
+};
+
+pub fn styled(s: impl Display + 'static, style: Style) -> Component {
+    Widget::elemental(
+        (s.to_string(), style),
+        |_, _| {},
+        |this| {
+            (
+                false,
+                Box::new(StringElement {
+                    s: this.state.0.clone(),
+                    cursor: None,
+                    selection: None,
+                    style: this.state.1,
+                }),
+            )
+        },
+    )
+}
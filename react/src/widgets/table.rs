@@ -0,0 +1,281 @@
+use std::{cmp::Ordering, fmt::Display, sync::Arc};
+
+use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEventKind};
+use stdext::prelude::*;
+
+use crate::{
+    component::prelude::*,
+    elements::table_element::first_visible,
+    focus::prelude::*,
+    message::prelude::*,
+    mouse::{self, Mouse},
+    prelude::{SortOrder, TableColumn, TableElement, on_reuse},
+    widget::Widget,
+};
+
+/// Bubbled up from the table with the id `table` when Enter is pressed on a row, with the index
+/// the row had in `rows`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowActivated {
+    pub table: usize,
+    pub row: usize,
+}
+
+/// The columns and rows are shared with the elements drawn from them
+struct Table {
+    columns: Arc<Vec<TableColumn>>,
+    rows: Arc<Vec<Vec<String>>>,
+    /// Indices into `rows` in the order they are shown
+    order: Arc<Vec<usize>>,
+    cursor: usize,
+    sort: Option<(usize, SortOrder)>,
+    focused: bool,
+    dirty: bool,
+}
+
+/// Numbers compare by value, everything else as text
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+impl Table {
+    fn move_cursor(&mut self, cursor: usize) {
+        self.cursor = cursor.min(self.rows.len().saturating_sub(1));
+        self.dirty = true;
+    }
+    /// Sorts by `column`, reversing the order if the rows already are. The cursor stays on its row.
+    fn sort_by(&mut self, column: usize) {
+        if column >= self.columns.len() {
+            return;
+        }
+        let order = match self.sort {
+            Some((sorted, SortOrder::Ascending)) if sorted == column => SortOrder::Descending,
+            _ => SortOrder::Ascending,
+        };
+        let current = self.order.get(self.cursor).copied();
+        self.sort_rows(column, order);
+        if let Some(current) = current {
+            self.cursor = self
+                .order
+                .iter()
+                .position(|&row| row == current)
+                .unwrap_or(0);
+        }
+    }
+    fn sort_rows(&mut self, column: usize, order: SortOrder) {
+        let rows = &self.rows;
+        let cell = |row: usize| rows[row].get(column).map_or("", String::as_str);
+        Arc::make_mut(&mut self.order).sort_by(|&a, &b| {
+            let ordering = compare(cell(a), cell(b));
+            match order {
+                SortOrder::Ascending => ordering,
+                SortOrder::Descending => ordering.reverse(),
+            }
+        });
+        self.sort = Some((column, order));
+        self.dirty = true;
+    }
+    /// Takes the rows of a rebuilt table, keeping the sort and the cursor where they still fit
+    fn reuse(&mut self, fresh: &mut Table) {
+        std::mem::swap(&mut self.columns, &mut fresh.columns);
+        std::mem::swap(&mut self.rows, &mut fresh.rows);
+        self.order = Arc::new((0..self.rows.len()).collect());
+        match self.sort {
+            Some((column, order)) if column < self.columns.len() => self.sort_rows(column, order),
+            _ => self.sort = None,
+        }
+        self.move_cursor(self.cursor);
+    }
+}
+
+/// A focusable table with a header row. Up/Down/Home/End and clicks move the row cursor,
+/// the digit keys sort by the corresponding column and Enter bubbles up `RowActivated`.
+pub fn table(
+    columns: impl IntoIterator<Item = impl Into<TableColumn>>,
+    rows: impl IntoIterator<Item = impl IntoIterator<Item = impl Display>>,
+) -> Component {
+    let rows = rows
+        .into_iter()
+        .map(|row| row.into_iter().map(|cell| cell.to_string()).collect())
+        .collect::<Vec<Vec<_>>>();
+    let table = Widget::elemental(
+        Table {
+            columns: Arc::new(columns.into_iter().map(Into::into).collect()),
+            order: Arc::new((0..rows.len()).collect()),
+            rows: Arc::new(rows),
+            cursor: 0,
+            sort: None,
+            focused: false,
+            dirty: false,
+        },
+        |this, msg| {
+            let id = this.id();
+            let table = &mut this.state;
+            switch(msg)
+                .case(|event: &KeyEvent| match event.code {
+                    KeyCode::Up => table.move_cursor(table.cursor.saturating_sub(1)),
+                    KeyCode::Down => table.move_cursor(table.cursor + 1),
+                    KeyCode::Home => table.move_cursor(0),
+                    KeyCode::End => table.move_cursor(usize::MAX),
+                    KeyCode::Char(c @ '1'..='9') => table.sort_by(c as usize - '1' as usize),
+                    KeyCode::Enter => {
+                        if let Some(&row) = table.order.get(table.cursor) {
+                            bubble(id, RowActivated { table: id, row });
+                        }
+                    }
+                    _ => {}
+                })
+                .case(|event: &Mouse| {
+                    let Some(region) = mouse::region(id) else {
+                        return;
+                    };
+                    if event.kind == MouseEventKind::Down(MouseButton::Left)
+                        && event.position.y >= 1
+                    {
+                        let first = first_visible(table.cursor, region.size.y - 1);
+                        let row = first + event.position.y as usize - 1;
+                        if row < table.rows.len() {
+                            table.move_cursor(row);
+                        }
+                    }
+                })
+                .case(|&Focused(focused)| {
+                    if focused == id {
+                        table.focused = true;
+                        table.dirty = true;
+                    }
+                })
+                .case(|&Blurred(blurred)| {
+                    if blurred == id {
+                        table.focused = false;
+                        table.dirty = true;
+                    }
+                });
+        },
+        |this| {
+            let table = &mut this.state;
+            (
+                std::mem::take(&mut table.dirty),
+                Box::new(TableElement {
+                    columns: table.columns.clone(),
+                    rows: table.rows.clone(),
+                    order: table.order.clone(),
+                    cursor: table.cursor,
+                    highlight: table.focused,
+                    sort: table.sort,
+                }),
+            )
+        },
+    );
+    focusable(on_reuse(table, Table::reuse))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::{
+        Alignment, ColumnWidth, Harness, Point, Size, Style, column, fixed, flex, text_field,
+    };
+
+    fn results() -> Component {
+        table(
+            [
+                TableColumn::new("day"),
+                TableColumn::new("time")
+                    .width(ColumnWidth::Fixed(6))
+                    .align(Alignment::Right),
+            ],
+            [["one", "12.5"], ["two", "3"], ["three", "100"]],
+        )
+    }
+
+    fn mount() -> Harness {
+        Harness::mount(results(), Size { x: 14, y: 3 })
+    }
+
+    #[test]
+    fn header_and_rows() {
+        mount()
+            .lines()
+            .must_be(vec!["day     time", "one     12.5", "two        3"]);
+    }
+
+    #[test]
+    fn cursor_scrolls_the_rows() {
+        let mut harness = mount();
+        harness.press(KeyCode::Down).press(KeyCode::Down);
+        harness
+            .lines()
+            .must_be(vec!["day     time", "two        3", "three    100"]);
+        harness
+            .cell(Point { x: 0, y: 2 })
+            .and_then(|token| token.style())
+            .must_be(Some(Style::default().reverse()));
+    }
+
+    #[test]
+    fn digits_sort_and_toggle() {
+        let mut harness = mount();
+        harness.press(KeyCode::Char('2'));
+        harness
+            .lines()
+            .must_be(vec!["day   time ▲", "two        3", "one     12.5"]);
+        harness.press(KeyCode::Char('2'));
+        harness
+            .lines()
+            .must_be(vec!["day   time ▼", "three    100", "one     12.5"]);
+        harness.press(KeyCode::Char('1'));
+        harness.lines()[1].clone().must_be("one     12.5");
+    }
+
+    #[test]
+    fn enter_reports_the_original_row() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let recorder = log.clone();
+        let table = results();
+        let parent = Widget::stateful(
+            (),
+            move |_, msg| {
+                switch(msg).case(|&RowActivated { row, .. }| recorder.borrow_mut().push(row));
+                Propagate
+            },
+            move |_| table.clone(),
+        );
+        let mut harness = Harness::mount(parent, Size { x: 14, y: 4 });
+        harness
+            .press(KeyCode::Char('1'))
+            .press(KeyCode::Char('1'))
+            .press(KeyCode::Home)
+            .press(KeyCode::Enter);
+        log.borrow().clone().must_be(vec![1]);
+    }
+
+    #[test]
+    fn clicks_hit_the_rows_shown_while_unfocused() {
+        let (field, _) = text_field("");
+        let mut harness = Harness::mount(
+            column([flex(1, results()), fixed(1, field)]),
+            Size { x: 14, y: 4 },
+        );
+        harness
+            .press(KeyCode::Down)
+            .press(KeyCode::Down)
+            .press(KeyCode::Tab);
+        harness.lines()[1..3]
+            .to_vec()
+            .must_be(vec!["two        3", "three    100"]);
+        harness.click(Point { x: 0, y: 1 });
+        harness.lines()[2].clone().must_be("two        3");
+        harness
+            .cell(Point { x: 0, y: 2 })
+            .and_then(|token| token.style())
+            .must_be(Some(Style::default().reverse()));
+    }
+}
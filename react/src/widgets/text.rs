@@ -1,6 +1,10 @@
 use std::fmt::Display;
 
-use crate::{component::prelude::*, prelude::StringElement, widget::Widget};
+use crate::{
+    component::prelude::*,
+    prelude::{StringElement, Style},
+    widget::Widget,
+};
 
 #[inline]
 pub fn text(s: impl Display + 'static) -> Component {
@@ -13,6 +17,8 @@ pub fn text(s: impl Display + 'static) -> Component {
                 Box::new(StringElement {
                     s: this.state.clone(),
                     cursor: None,
+                    selection: None,
+                    style: Style::default(),
                 }),
             )
         },
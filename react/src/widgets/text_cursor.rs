@@ -1,13 +1,22 @@
-use std::fmt::Display;
+use std::{fmt::Display, ops::Range};
 
 use crate::{
-    prelude::{Component, StringElement},
+    prelude::{Component, StringElement, Style},
     widget::Widget,
 };
 
 pub fn text_cursor(s: impl Display + 'static, cursor: Option<usize>) -> Component {
+    text_selection(s, cursor, None)
+}
+
+/// Text with a cursor and the graphemes in `selection` highlighted
+pub fn text_selection(
+    s: impl Display + 'static,
+    cursor: Option<usize>,
+    selection: Option<Range<usize>>,
+) -> Component {
     Widget::elemental(
-        (s.to_string(), cursor),
+        (s.to_string(), cursor, selection),
         |_, _| (),
         |this| {
             (
@@ -15,6 +24,8 @@ pub fn text_cursor(s: impl Display + 'static, cursor: Option<usize>) -> Componen
                 Box::new(StringElement {
                     s: this.state.0.clone(),
                     cursor: this.state.1,
+                    selection: this.state.2.clone(),
+                    style: Style::default(),
                 }),
             )
         },
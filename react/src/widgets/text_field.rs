@@ -1,98 +1,1100 @@
-use std::{cell::RefCell, fmt::Display, rc::Rc};
+use std::{
+    cell::{Ref, RefCell},
+    collections::VecDeque,
+    fmt::Display,
+    ops::{Deref, Range},
+    rc::Rc,
+};
 
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEventKind};
 use stdext::prelude::*;
 
-use crate::{component::prelude::*, message::prelude::*, prelude::text_cursor, widget::prelude::*};
+use crate::{
+    clipboard::{self, Paste},
+    component::prelude::*,
+    elements::string_element::index_at,
+    focus::prelude::*,
+    keymap::{Action, Keymap, with_keymap},
+    message::prelude::*,
+    mouse::{self, prelude::*},
+    prelude::{Point, on_reuse, text_selection},
+    unicode::{graphemes, is_newline, width},
+    widget::prelude::*,
+};
 
+const UNDO: &str = "text_field.undo";
+const REDO: &str = "text_field.redo";
+const SELECT_ALL: &str = "text_field.select_all";
+const COPY: &str = "text_field.copy";
+const CUT: &str = "text_field.cut";
+const PASTE: &str = "text_field.paste";
+
+fn keymap() -> Keymap {
+    Keymap::new()
+        .bind(UNDO, "Ctrl+Z")
+        .bind(REDO, "Ctrl+Y")
+        .bind(REDO, "Ctrl+Shift+Z")
+        .bind(SELECT_ALL, "Ctrl+A")
+        .bind(COPY, "Ctrl+Insert")
+        .bind(CUT, "Ctrl+X")
+        .bind(CUT, "Shift+Delete")
+        .bind(PASTE, "Ctrl+V")
+        .bind(PASTE, "Shift+Insert")
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TextFieldOptions {
+    /// How many undo steps are kept before the oldest ones are forgotten
+    pub undo_limit: usize,
+    /// How many bytes of text the undo steps may hold before the oldest ones are forgotten.
+    /// A single edit larger than that can't be undone.
+    pub undo_bytes: usize,
+}
+
+impl Default for TextFieldOptions {
+    fn default() -> Self {
+        Self {
+            undo_limit: 1000,
+            undo_bytes: 1 << 20,
+        }
+    }
+}
+
+/// A change to the buffer, in bytes, with the cursor positions around it
+#[derive(Debug, Clone)]
+struct Edit {
+    at: usize,
+    removed: String,
+    inserted: String,
+    cursor_before: usize,
+    cursor_after: usize,
+}
+
+impl Edit {
+    /// Bytes of text kept to undo and redo it
+    fn size(&self) -> usize {
+        self.removed.len() + self.inserted.len()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct History {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    options: TextFieldOptions,
+    /// Bytes of text held by the undo steps
+    bytes: usize,
+    /// Whether the last step is typing that the next typed character may join
+    typing: bool,
+}
+
+impl History {
+    fn record(&mut self, edit: Edit, typing: bool) {
+        self.redo.clear();
+        let joins = typing
+            && self.typing
+            && edit.removed.is_empty()
+            && self
+                .undo
+                .back()
+                .is_some_and(|last| last.at + last.inserted.len() == edit.at);
+        self.typing = typing;
+        match self.undo.back_mut() {
+            Some(last) if joins => {
+                last.inserted.push_str(&edit.inserted);
+                last.cursor_after = edit.cursor_after;
+                self.bytes += edit.inserted.len();
+                self.forget();
+            }
+            _ => self.push(edit),
+        }
+    }
+    /// Adds an undo step, forgetting the oldest ones that no longer fit
+    fn push(&mut self, edit: Edit) {
+        self.bytes += edit.size();
+        self.undo.push_back(edit);
+        self.forget();
+    }
+    fn pop(&mut self) -> Option<Edit> {
+        let edit = self.undo.pop_back()?;
+        self.bytes -= edit.size();
+        Some(edit)
+    }
+    fn forget(&mut self) {
+        while self.undo.len() > self.options.undo_limit || self.bytes > self.options.undo_bytes {
+            let Some(edit) = self.undo.pop_front() else {
+                break;
+            };
+            self.bytes -= edit.size();
+        }
+    }
+}
+
+/// Where each grapheme of a text starts, kept up to date as the text is edited
+#[derive(Debug, Clone, Default)]
+struct GraphemeIndex {
+    /// Byte offsets of the graphemes, followed by the length of the text
+    starts: Vec<usize>,
+    /// The edit of the field the offsets are up to date with
+    revision: u64,
+}
+
+/// Where the graphemes of `s` start, counting from `offset`
+fn starts(s: &str, offset: usize) -> impl Iterator<Item = usize> {
+    graphemes(s).scan(offset, |at, grapheme| {
+        let start = *at;
+        *at += grapheme.len();
+        Some(start)
+    })
+}
+
+impl GraphemeIndex {
+    fn new(text: &str, revision: u64) -> Self {
+        let mut starts = starts(text, 0).collect::<Vec<_>>();
+        starts.push(text.len());
+        Self { starts, revision }
+    }
+    fn len(&self) -> usize {
+        self.starts.len() - 1
+    }
+    /// Byte offset of the grapheme at `index`, or the end of the text past the last one
+    fn byte(&self, index: usize) -> usize {
+        self.starts[index.min(self.len())]
+    }
+    /// How many graphemes start before byte `at`
+    fn before(&self, at: usize) -> usize {
+        self.starts.partition_point(|&start| start < at)
+    }
+    /// Follows `text`, in which the bytes in `range` were just replaced by `inserted` bytes.
+    /// A grapheme always ends after a line feed, so only the lines the edit touched are
+    /// segmented again and the offsets after them are shifted.
+    fn splice(&mut self, text: &str, range: Range<usize>, inserted: usize) {
+        let end = range.start + inserted;
+        let from = text[..range.start]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1);
+        let to = text[end..]
+            .find('\n')
+            .map_or(text.len(), |newline| end + newline + 1);
+        let shift = |start: usize| start + inserted - range.len();
+        let (kept, old_to) = (
+            self.starts.partition_point(|&start| start < from),
+            to + range.len() - inserted,
+        );
+        let after = self.starts.partition_point(|&start| start < old_to);
+        let shifted = self.starts[after..]
+            .iter()
+            .map(|&start| shift(start))
+            .collect::<Vec<_>>();
+        self.starts.truncate(kept);
+        self.starts.extend(starts(&text[from..to], from));
+        self.starts.extend(shifted);
+    }
+}
+
+/// The buffer together with its grapheme index
+struct Graphemes<'a> {
+    text: Ref<'a, String>,
+    index: Ref<'a, GraphemeIndex>,
+}
+
+impl Deref for Graphemes<'_> {
+    type Target = GraphemeIndex;
+    fn deref(&self) -> &GraphemeIndex {
+        &self.index
+    }
+}
+
+impl Graphemes<'_> {
+    fn get(&self, index: usize) -> &str {
+        &self.text[self.starts[index]..self.starts[index + 1]]
+    }
+    fn slice(
+        &self,
+        range: Range<usize>,
+    ) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+        self.starts[range.start..=range.end]
+            .windows(2)
+            .map(|bounds| &self.text[bounds[0]..bounds[1]])
+    }
+}
+
+/// An editable buffer with a cursor. Positions count grapheme clusters, not bytes.
 #[derive(Debug, Clone)]
 struct TextField {
     buffer: Rc<RefCell<String>>,
     cursor: usize,
+    /// The other end of the selection, which runs from here to the cursor
+    anchor: Option<usize>,
+    /// Column that Up and Down try to return to, kept while moving across shorter lines
+    column: Option<usize>,
     show_cursor: bool,
+    history: History,
+    /// Counts the edits made through the field, so the index knows when to follow
+    revision: u64,
+    index: RefCell<GraphemeIndex>,
+}
+
+fn is_space(grapheme: &str) -> bool {
+    grapheme.chars().all(char::is_whitespace)
 }
 
 impl TextField {
-    fn insert(&mut self, c: char) {
-        self.buffer.borrow_mut().insert(self.cursor, c);
-        self.cursor += 1;
+    fn new(buffer: Rc<RefCell<String>>, options: TextFieldOptions) -> Self {
+        let index = GraphemeIndex::new(&buffer.borrow(), 0);
+        Self {
+            buffer,
+            cursor: index.len(),
+            anchor: None,
+            column: None,
+            show_cursor: false,
+            history: History {
+                options,
+                ..Default::default()
+            },
+            revision: 0,
+            index: RefCell::new(index),
+        }
     }
-    fn remove_left(&mut self) {
-        if self.cursor > 0 {
-            self.buffer.borrow_mut().remove(self.cursor - 1);
-            self.cursor -= 1;
+
+    /// Moves the text into the buffer of a rebuilt field, which its caller holds on to
+    fn reuse(&mut self, fresh: &mut TextField) {
+        fresh.buffer.replace(self.buffer.take());
+        self.buffer = fresh.buffer.clone();
+        self.history.options = fresh.history.options;
+        self.history.forget();
+        self.revision += 1;
+    }
+
+    /// The graphemes of the buffer. They are indexed again after edits made around the field,
+    /// which are noticed by the length of the text.
+    fn graphemes(&self) -> Graphemes<'_> {
+        let text = self.buffer.borrow();
+        let stale = {
+            let index = self.index.borrow();
+            index.revision != self.revision || index.starts.last() != Some(&text.len())
+        };
+        if stale {
+            self.index.replace(GraphemeIndex::new(&text, self.revision));
         }
+        Graphemes {
+            text,
+            index: self.index.borrow(),
+        }
+    }
+    /// The cursor, kept inside the buffer in case it was changed from outside
+    fn cursor(&self) -> usize {
+        self.cursor.min(self.graphemes().len())
+    }
+    fn byte(&self, index: usize) -> usize {
+        self.graphemes().byte(index)
+    }
+    /// The selected graphemes, unless the selection is empty
+    fn selection(&self) -> Option<Range<usize>> {
+        let cursor = self.cursor();
+        let anchor = self.anchor?.min(self.graphemes().len());
+        (anchor != cursor).then(|| anchor.min(cursor)..anchor.max(cursor))
+    }
+    fn selected_text(&self) -> Option<String> {
+        let selection = self.selection()?;
+        let (start, end) = (self.byte(selection.start), self.byte(selection.end));
+        Some(self.buffer.borrow()[start..end].to_string())
+    }
+    /// Starts selecting from the cursor unless a selection is already being extended
+    fn extend_selection(&mut self) {
+        if self.anchor.is_none() {
+            self.anchor = Some(self.cursor());
+        }
+    }
+    fn select_all(&mut self) {
+        self.anchor = Some(0);
+        let end = self.graphemes().len();
+        self.move_to(end);
+    }
+    /// Removes the selection, returning whether there was one
+    fn remove_selection(&mut self) -> bool {
+        let Some(selection) = self.selection() else {
+            return false;
+        };
+        self.replace(selection, "");
+        true
+    }
+    fn copy(&self) {
+        if let Some(selected) = self.selected_text() {
+            clipboard::copy(selected);
+        }
+    }
+    fn cut(&mut self) {
+        self.copy();
+        self.remove_selection();
+    }
+    fn paste(&mut self, s: &str) {
+        self.insert(&s.replace("\r\n", "\n").replace('\r', "\n"));
+    }
+
+    /// Replaces the graphemes in `range` by `with`, leaving the cursor right after it
+    fn replace(&mut self, range: Range<usize>, with: &str) {
+        self.edit(range, with, false);
+    }
+    fn edit(&mut self, range: Range<usize>, with: &str, typing: bool) {
+        let cursor_before = self.cursor();
+        self.anchor = None;
+        let (start, end) = (self.byte(range.start), self.byte(range.end));
+        let removed = self.splice(start..end, with);
+        if !removed.is_empty() || !with.is_empty() {
+            let edit = Edit {
+                at: start,
+                removed,
+                inserted: with.to_string(),
+                cursor_before,
+                cursor_after: self.cursor,
+            };
+            self.history.record(edit, typing);
+        }
+    }
+    /// Replaces the bytes in `range` by `with` and moves the cursor after it, returning what was there
+    fn splice(&mut self, range: Range<usize>, with: &str) -> String {
+        // Catches up with outside changes first, so the index can follow this edit
+        drop(self.graphemes());
+        let mut buffer = self.buffer.borrow_mut();
+        let start = range.start;
+        let removed = buffer[range.clone()].to_string();
+        buffer.replace_range(range.clone(), with);
+        self.revision += 1;
+        let index = self.index.get_mut();
+        index.splice(&buffer, range, with.len());
+        index.revision = self.revision;
+        // Inserted combining marks can merge with the grapheme before them
+        self.cursor = index.before(start + with.len());
+        self.column = None;
+        removed
+    }
+    /// Where inserted text goes: over the selection, or else at the cursor
+    fn insertion(&self) -> Range<usize> {
+        let cursor = self.cursor();
+        self.selection().unwrap_or(cursor..cursor)
+    }
+    fn insert(&mut self, s: &str) {
+        self.replace(self.insertion(), s);
+    }
+    fn type_str(&mut self, s: &str) {
+        self.edit(self.insertion(), s, true);
     }
-    fn insert_str(&mut self, s: &str) {
-        self.buffer.borrow_mut().insert_str(self.cursor, s);
-        self.cursor += s.len();
+    /// Whether the buffer still holds `s` at byte `at`. It may have been changed from outside.
+    fn holds(&self, at: usize, s: &str) -> bool {
+        self.buffer.borrow().get(at..at + s.len()) == Some(s)
     }
-    fn move_cursor_left(&mut self) {
-        if self.cursor > 0 {
-            self.cursor -= 1;
+    fn undo(&mut self) {
+        let Some(edit) = self.history.pop() else {
+            return;
+        };
+        if !self.holds(edit.at, &edit.inserted) {
+            self.history = History {
+                options: self.history.options,
+                ..Default::default()
+            };
+            return;
         }
+        self.splice(edit.at..edit.at + edit.inserted.len(), &edit.removed);
+        self.cursor = edit.cursor_before;
+        self.anchor = None;
+        self.history.typing = false;
+        self.history.redo.push(edit);
     }
-    fn move_cursor_right(&mut self) {
-        if self.cursor < self.buffer.borrow().len() {
-            self.cursor += 1;
+    fn redo(&mut self) {
+        let Some(edit) = self.history.redo.pop() else {
+            return;
+        };
+        if !self.holds(edit.at, &edit.removed) {
+            self.history.redo.clear();
+            return;
+        }
+        self.splice(edit.at..edit.at + edit.removed.len(), &edit.inserted);
+        self.cursor = edit.cursor_after;
+        self.anchor = None;
+        self.history.typing = false;
+        self.history.push(edit);
+    }
+    fn remove_left(&mut self) {
+        if self.remove_selection() {
+            return;
+        }
+        let cursor = self.cursor();
+        if cursor > 0 {
+            self.replace(cursor - 1..cursor, "");
+        }
+    }
+    fn remove_right(&mut self) {
+        if self.remove_selection() {
+            return;
+        }
+        let cursor = self.cursor();
+        if cursor < self.graphemes().len() {
+            self.replace(cursor..cursor + 1, "");
         }
     }
-    // does not work
     fn remove_word_left(&mut self) {
-        let space_idx = self
-            .buffer
-            .borrow()
-            .chars()
-            .enumerate()
-            .filter_map(|(i, c)| {
-                if c == ' ' && i < self.cursor {
-                    Some(i)
-                } else {
-                    None
+        if self.remove_selection() {
+            return;
+        }
+        let cursor = self.cursor();
+        self.replace(self.word_left(cursor)..cursor, "");
+    }
+    fn remove_line_left(&mut self) {
+        if self.remove_selection() {
+            return;
+        }
+        let cursor = self.cursor();
+        self.replace(self.line_start(cursor)..cursor, "");
+    }
+
+    fn move_to(&mut self, cursor: usize) {
+        self.cursor = cursor;
+        self.column = None;
+        self.history.typing = false;
+    }
+    fn move_left(&mut self) {
+        self.move_to(self.cursor().saturating_sub(1));
+    }
+    fn move_right(&mut self) {
+        let end = self.graphemes().len();
+        self.move_to((self.cursor() + 1).min(end));
+    }
+    fn move_word_left(&mut self) {
+        self.move_to(self.word_left(self.cursor()));
+    }
+    fn move_word_right(&mut self) {
+        self.move_to(self.word_right(self.cursor()));
+    }
+    fn move_home(&mut self) {
+        self.move_to(self.line_start(self.cursor()));
+    }
+    fn move_end(&mut self) {
+        self.move_to(self.line_end(self.cursor()));
+    }
+    fn move_up(&mut self) {
+        let start = self.line_start(self.cursor());
+        if start == 0 {
+            return self.move_to(0);
+        }
+        let column = self.column.unwrap_or_else(|| self.column_of(self.cursor()));
+        self.move_to(self.at_column(self.line_start(start - 1), column));
+        self.column = Some(column);
+    }
+    fn move_down(&mut self) {
+        let end = self.line_end(self.cursor());
+        if end == self.graphemes().len() {
+            return self.move_to(end);
+        }
+        let column = self.column.unwrap_or_else(|| self.column_of(self.cursor()));
+        self.move_to(self.at_column(end + 1, column));
+        self.column = Some(column);
+    }
+    /// Moves the cursor to `at`, selecting the text passed over when `select`ing
+    fn click(&mut self, width: isize, at: Point, select: bool) {
+        if select {
+            self.extend_selection();
+        } else {
+            self.anchor = None;
+        }
+        let index = index_at(&self.buffer.borrow(), width, at);
+        self.move_to(index);
+    }
+
+    /// Start of the line `index` is on
+    fn line_start(&self, index: usize) -> usize {
+        self.graphemes()
+            .slice(0..index)
+            .rposition(is_newline)
+            .map_or(0, |newline| newline + 1)
+    }
+    /// Index of the newline ending the line `index` is on, or the end of the buffer
+    fn line_end(&self, index: usize) -> usize {
+        let graphemes = self.graphemes();
+        graphemes
+            .slice(index..graphemes.len())
+            .position(is_newline)
+            .map_or(graphemes.len(), |newline| index + newline)
+    }
+    /// Display column of `index` within its line
+    fn column_of(&self, index: usize) -> usize {
+        self.graphemes()
+            .slice(self.line_start(index)..index)
+            .map(width)
+            .sum()
+    }
+    /// The position on the line starting at `start` that is closest to `column` without passing it
+    fn at_column(&self, start: usize, column: usize) -> usize {
+        let end = self.line_end(start);
+        let mut reached = 0;
+        for (index, g) in self.graphemes().slice(start..end).enumerate() {
+            reached += width(g);
+            if reached > column {
+                return start + index;
+            }
+        }
+        end
+    }
+    /// Start of the word before `index`, skipping whitespace in between
+    fn word_left(&self, index: usize) -> usize {
+        let graphemes = self.graphemes();
+        let mut index = index;
+        while index > 0 && is_space(graphemes.get(index - 1)) {
+            index -= 1;
+        }
+        while index > 0 && !is_space(graphemes.get(index - 1)) {
+            index -= 1;
+        }
+        index
+    }
+    /// End of the word after `index`, skipping whitespace in between
+    fn word_right(&self, index: usize) -> usize {
+        let graphemes = self.graphemes();
+        let mut index = index;
+        while index < graphemes.len() && is_space(graphemes.get(index)) {
+            index += 1;
+        }
+        while index < graphemes.len() && !is_space(graphemes.get(index)) {
+            index += 1;
+        }
+        index
+    }
+
+    fn action(&mut self, action: &str) {
+        match action {
+            UNDO => self.undo(),
+            REDO => self.redo(),
+            SELECT_ALL => self.select_all(),
+            COPY => self.copy(),
+            CUT => self.cut(),
+            PASTE => self.paste(&clipboard::contents()),
+            _ => {}
+        }
+    }
+
+    fn key(&mut self, event: &KeyEvent) {
+        let control = event.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = event.modifiers.contains(KeyModifiers::SHIFT);
+        let moves = matches!(
+            event.code,
+            KeyCode::Left
+                | KeyCode::Right
+                | KeyCode::Up
+                | KeyCode::Down
+                | KeyCode::Home
+                | KeyCode::End
+        );
+        if moves && shift {
+            self.extend_selection();
+        } else if moves {
+            // Plain Left and Right leave a selection at its start and end
+            let selection = self.selection();
+            self.anchor = None;
+            match (selection, event.code) {
+                (Some(selection), KeyCode::Left) if !control => {
+                    return self.move_to(selection.start);
+                }
+                (Some(selection), KeyCode::Right) if !control => {
+                    return self.move_to(selection.end);
                 }
-            })
-            .max()
-            .unwrap_or(0);
-        while self.cursor > space_idx {
-            self.remove_left();
+                _ => {}
+            }
+        }
+        match event.code {
+            KeyCode::Left if control => self.move_word_left(),
+            KeyCode::Right if control => self.move_word_right(),
+            KeyCode::Backspace if control => self.remove_word_left(),
+            KeyCode::Char('w') if control => self.remove_word_left(),
+            KeyCode::Char('u') if control => self.remove_line_left(),
+            KeyCode::Left => self.move_left(),
+            KeyCode::Right => self.move_right(),
+            KeyCode::Up => self.move_up(),
+            KeyCode::Down => self.move_down(),
+            KeyCode::Home => self.move_home(),
+            KeyCode::End => self.move_end(),
+            KeyCode::Backspace => self.remove_left(),
+            KeyCode::Delete => self.remove_right(),
+            KeyCode::Enter => self.insert("\n"),
+            KeyCode::Tab => self.insert("    "),
+            KeyCode::Char(c) if event.modifiers.difference(KeyModifiers::SHIFT).is_empty() => {
+                self.type_str(c.encode_utf8(&mut [0; 4]))
+            }
+            _ => {}
         }
     }
 }
 
 pub fn text_field(initial: impl Display) -> (Component, Rc<RefCell<String>>) {
-    let initial = initial.to_string();
-    let len = initial.len();
-    let initial = Rc::new(RefCell::new(initial));
+    text_field_with(TextFieldOptions::default(), initial)
+}
+
+/// An editable, focusable text buffer, also returned so the caller can read it.
+/// Ctrl+Z undoes and Ctrl+Y or Ctrl+Shift+Z redoes; consecutive typing is undone in one step.
+/// Shift with the arrow keys or dragging the mouse selects, and Ctrl+Insert, Ctrl+X and Ctrl+V
+/// copy, cut and paste through the clipboard register, leaving Ctrl+C to quit.
+/// Pasted text arrives in one piece as `Paste`.
+/// These are the actions `text_field.undo`, `.redo`, `.select_all`, `.copy`, `.cut` and `.paste`,
+/// which apps can `remap`.
+pub fn text_field_with(
+    options: TextFieldOptions,
+    initial: impl Display,
+) -> (Component, Rc<RefCell<String>>) {
+    let buffer = Rc::new(RefCell::new(initial.to_string()));
     (
-        Widget::stateful(
-            TextField {
-                cursor: len,
-                buffer: initial.clone(),
-                show_cursor: true,
-            },
-            |this, msg| {
-                switch(msg).case(|event: &KeyEvent| match event.code {
-                    KeyCode::Enter => this.set_state(|buffer| buffer.insert('\n')),
-                    KeyCode::Backspace => this.set_state(|buffer| _ = buffer.remove_left()),
-                    KeyCode::Char(c) => this.set_state(|buffer| buffer.insert(c)),
-                    KeyCode::Left => this.set_state(|state| state.move_cursor_left()),
-                    KeyCode::Right => this.set_state(|state| state.move_cursor_right()),
-                    KeyCode::Tab => this.set_state(|state| state.insert_str("    ")),
-                    _ => {}
-                });
-                Intercept
-            },
-            |buffer| {
-                text_cursor(
-                    buffer.buffer.borrow().clone(),
-                    if buffer.show_cursor {
-                        Some(buffer.cursor)
-                    } else {
-                        None
+        with_keymap(
+            focusable(on_reuse(
+                Widget::stateful(
+                    TextField::new(buffer.clone(), options),
+                    |this, msg| {
+                        let id = this.id();
+                        switch(msg)
+                            .case(|event: &KeyEvent| this.set_state(|state| state.key(event)))
+                            .case(|&Action(action)| this.set_state(|state| state.action(action)))
+                            .case(|Paste(s): &Paste| this.set_state(|state| state.paste(s)))
+                            .case(|event: &Mouse| {
+                                let width = mouse::region(id).map_or(0, |rect| rect.size.x);
+                                match event.kind {
+                                    MouseEventKind::Down(MouseButton::Left) => {
+                                        let select = event.modifiers.contains(KeyModifiers::SHIFT);
+                                        this.set_state(|state| {
+                                            state.click(width, event.position, select)
+                                        })
+                                    }
+                                    MouseEventKind::Drag(MouseButton::Left) => {
+                                        this.set_state(|state| {
+                                            state.click(width, event.position, true)
+                                        })
+                                    }
+                                    _ => {}
+                                }
+                            })
+                            .case(|&Focused(focused)| {
+                                if focused == id {
+                                    this.set_state(|state| state.show_cursor = true)
+                                }
+                            })
+                            .case(|&Blurred(blurred)| {
+                                if blurred == id {
+                                    this.set_state(|state| state.show_cursor = false)
+                                }
+                            });
+                        Intercept
                     },
-                )
-            },
+                    |state| {
+                        text_selection(
+                            state.buffer.borrow().clone(),
+                            state.show_cursor.then(|| state.cursor()),
+                            state.selection(),
+                        )
+                    },
+                ),
+                TextField::reuse,
+            )),
+            keymap(),
         ),
-        initial,
+        buffer,
     )
 }
+
+#[cfg(test)]
+mod test {
+    use stdext::prelude::*;
+
+    use super::*;
+    use crate::prelude::{Harness, Point, Size};
+
+    fn mount(initial: &str) -> (Harness, Rc<RefCell<String>>) {
+        let (field, buffer) = text_field(initial);
+        (Harness::mount(field, Size { x: 10, y: 3 }), buffer)
+    }
+
+    #[test]
+    fn typing_appends_at_the_cursor() {
+        let (mut harness, buffer) = mount("ab");
+        harness.cursor().must_be(Some(Point { x: 2, y: 0 }));
+        harness.type_str("cd");
+        harness.text().must_be("abcd");
+        buffer.borrow().clone().must_be("abcd");
+        harness.cursor().must_be(Some(Point { x: 4, y: 0 }));
+    }
+
+    #[test]
+    fn moving_and_deleting() {
+        let (mut harness, _) = mount("abc");
+        harness.press(KeyCode::Left).press(KeyCode::Backspace);
+        harness.text().must_be("ac");
+        harness.cursor().must_be(Some(Point { x: 1, y: 0 }));
+        harness
+            .press(KeyCode::Right)
+            .press(KeyCode::Right)
+            .type_str("!");
+        harness.text().must_be("ac!");
+    }
+
+    #[test]
+    fn enter_starts_a_new_line() {
+        let (mut harness, _) = mount("hi");
+        harness.press(KeyCode::Enter).type_str("there");
+        harness.text().must_be("hi\nthere");
+        harness
+            .find("there")
+            .map(|rect| rect.origin)
+            .must_be(Some(Point { x: 0, y: 1 }));
+    }
+
+    #[test]
+    fn clicking_moves_the_cursor() {
+        let (mut harness, _) = mount("hello\nworld");
+        harness.click(Point { x: 2, y: 0 }).type_str("_");
+        harness.text().must_be("he_llo\nworld");
+        harness.click(Point { x: 8, y: 1 }).type_str("!");
+        harness.text().must_be("he_llo\nworld!");
+    }
+
+    fn field(s: &str) -> TextField {
+        TextField::new(Rc::new(RefCell::new(s.to_string())), Default::default())
+    }
+
+    fn with_cursor(field: &TextField) -> String {
+        let graphemes = field.graphemes();
+        let cursor = graphemes.byte(field.cursor());
+        format!(
+            "{}|{}",
+            &graphemes.text[..cursor],
+            &graphemes.text[cursor..]
+        )
+    }
+
+    fn ctrl(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::CONTROL)
+    }
+
+    #[test]
+    fn editing_works_on_graphemes() {
+        let mut field = field("añ中e\u{301}");
+        field.remove_left();
+        with_cursor(&field).must_be("añ中|");
+        field.move_left();
+        field.move_left();
+        field.insert("ü");
+        with_cursor(&field).must_be("aü|ñ中");
+        field.remove_right();
+        with_cursor(&field).must_be("aü|中");
+        field.move_to(2);
+        field.insert("👍🏽");
+        with_cursor(&field).must_be("aü👍🏽|中");
+    }
+
+    #[test]
+    fn combining_marks_join_the_grapheme_before() {
+        let mut field = field("e");
+        field.insert("\u{301}");
+        field.cursor.must_be(1);
+        field.graphemes().len().must_be(1);
+    }
+
+    #[test]
+    fn edits_keep_the_grapheme_index_up_to_date() {
+        let mut field = field("ab\nc中\nd");
+        let index = |field: &TextField| field.graphemes().starts.clone();
+        field.move_to(4);
+        field.insert("e\u{301}\nf");
+        field.move_to(1);
+        field.insert("\u{301}");
+        field.move_to(7);
+        field.remove_left();
+        index(&field).must_be(GraphemeIndex::new("a\u{301}b\nce\u{301}\n中\nd", 0).starts);
+        field.buffer.replace("x\ny".to_string());
+        index(&field).must_be(vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn line_breaks_can_join_across_an_edit() {
+        let mut field = field("a\rb\r\nc");
+        let index = |field: &TextField| field.graphemes().starts.clone();
+        field.move_to(2);
+        field.insert("\n");
+        index(&field).must_be(GraphemeIndex::new("a\r\nb\r\nc", 0).starts);
+        field.move_to(3);
+        field.insert("x\r");
+        index(&field).must_be(GraphemeIndex::new("a\r\nbx\r\r\nc", 0).starts);
+        field.move_to(4);
+        field.remove_right();
+        index(&field).must_be(GraphemeIndex::new("a\r\nbx\r\nc", 0).starts);
+        field.move_to(1);
+        field.remove_right();
+        with_cursor(&field).must_be("a|bx\r\nc");
+        index(&field).must_be(GraphemeIndex::new("abx\r\nc", 0).starts);
+    }
+
+    #[test]
+    fn up_and_down_remember_the_column() {
+        let mut field = field("中文abc\nx\nabcdef");
+        field.move_to(4);
+        field.column_of(4).must_be(6);
+        field.move_down();
+        with_cursor(&field).must_be("中文abc\nx|\nabcdef");
+        field.move_down();
+        with_cursor(&field).must_be("中文abc\nx\nabcdef|");
+        field.move_left();
+        field.move_up();
+        field.move_up();
+        with_cursor(&field).must_be("中文a|bc\nx\nabcdef");
+        field.move_up();
+        field.cursor.must_be(0);
+    }
+
+    #[test]
+    fn home_and_end_stay_on_the_line() {
+        let mut field = field("ab\ncd\nef");
+        field.move_to(4);
+        field.move_home();
+        field.cursor.must_be(3);
+        field.move_end();
+        field.cursor.must_be(5);
+    }
+
+    #[test]
+    fn word_movement_and_deletion() {
+        let mut field = field("déjà vu  ");
+        field.key(&ctrl(KeyCode::Left));
+        with_cursor(&field).must_be("déjà |vu  ");
+        field.key(&ctrl(KeyCode::Left));
+        with_cursor(&field).must_be("|déjà vu  ");
+        field.key(&ctrl(KeyCode::Right));
+        with_cursor(&field).must_be("déjà| vu  ");
+        field.move_end();
+        field.key(&ctrl(KeyCode::Char('w')));
+        with_cursor(&field).must_be("déjà |");
+        field.key(&ctrl(KeyCode::Backspace));
+        with_cursor(&field).must_be("|");
+    }
+
+    #[test]
+    fn ctrl_u_deletes_to_the_line_start() {
+        let mut field = field("keep\ndrop this");
+        field.key(&ctrl(KeyCode::Char('u')));
+        with_cursor(&field).must_be("keep\n|");
+    }
+
+    #[test]
+    fn control_chords_do_not_type() {
+        let mut field = field("");
+        field.key(&ctrl(KeyCode::Char('x')));
+        field.key(&KeyEvent::new(KeyCode::Char('A'), KeyModifiers::SHIFT));
+        with_cursor(&field).must_be("A|");
+    }
+
+    #[test]
+    fn wide_characters_in_the_harness() {
+        let (mut harness, buffer) = mount("中");
+        harness.cursor().must_be(Some(Point { x: 2, y: 0 }));
+        harness.press(KeyCode::Left).type_str("é");
+        buffer.borrow().clone().must_be("é中");
+        harness.cursor().must_be(Some(Point { x: 1, y: 0 }));
+    }
+
+    fn typed(field: &mut TextField, s: &str) {
+        for c in s.chars() {
+            field.key(&KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+    }
+
+    #[test]
+    fn typing_is_undone_in_one_step() {
+        let mut field = field("");
+        typed(&mut field, "hello");
+        field.move_left();
+        typed(&mut field, "ab");
+        with_cursor(&field).must_be("hellab|o");
+        field.action(UNDO);
+        with_cursor(&field).must_be("hell|o");
+        field.action(UNDO);
+        with_cursor(&field).must_be("|");
+        field.action(UNDO);
+        with_cursor(&field).must_be("|");
+    }
+
+    #[test]
+    fn undo_restores_deletions_and_the_cursor() {
+        let mut field = field("déjà vu");
+        field.move_to(3);
+        field.remove_left();
+        field.remove_left();
+        with_cursor(&field).must_be("d|à vu");
+        field.undo();
+        with_cursor(&field).must_be("dé|à vu");
+        field.undo();
+        with_cursor(&field).must_be("déj|à vu");
+    }
+
+    #[test]
+    fn redo_replays_until_something_new_is_done() {
+        let mut field = field("");
+        typed(&mut field, "ab");
+        field.insert("\n");
+        field.undo();
+        field.undo();
+        with_cursor(&field).must_be("|");
+        field.action(REDO);
+        with_cursor(&field).must_be("ab|");
+        field.action(REDO);
+        with_cursor(&field).must_be("ab\n|");
+        field.undo();
+        typed(&mut field, "c");
+        field.redo();
+        with_cursor(&field).must_be("abc|");
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut field = TextField::new(
+            Rc::new(RefCell::new(String::new())),
+            TextFieldOptions {
+                undo_limit: 2,
+                ..Default::default()
+            },
+        );
+        for s in ["a", "b", "c"] {
+            field.insert(s);
+        }
+        for _ in 0..3 {
+            field.undo();
+        }
+        with_cursor(&field).must_be("a|");
+    }
+
+    #[test]
+    fn history_is_capped_by_size() {
+        let options = TextFieldOptions {
+            undo_bytes: 4,
+            ..Default::default()
+        };
+        let mut field = TextField::new(Rc::new(RefCell::new(String::new())), options);
+        for s in ["ab", "cd", "e"] {
+            field.insert(s);
+        }
+        for _ in 0..3 {
+            field.undo();
+        }
+        with_cursor(&field).must_be("ab|");
+        let mut field = TextField::new(Rc::new(RefCell::new(String::new())), options);
+        // Typing past the size forgets the step, so what follows starts a new one
+        typed(&mut field, "abcdef");
+        field.undo();
+        field.undo();
+        with_cursor(&field).must_be("abcde|");
+    }
+
+    #[test]
+    fn outside_changes_drop_the_history() {
+        let mut field = field("");
+        typed(&mut field, "abc");
+        field.buffer.borrow_mut().clear();
+        field.undo();
+        with_cursor(&field).must_be("|");
+    }
+
+    fn shift(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::SHIFT)
+    }
+
+    #[test]
+    fn shift_selects_and_typing_replaces_the_selection() {
+        let mut field = field("hello world");
+        field.key(&shift(KeyCode::Left));
+        field.key(&KeyEvent::new(
+            KeyCode::Left,
+            KeyModifiers::SHIFT | KeyModifiers::CONTROL,
+        ));
+        field.selected_text().must_be(Some("world".to_string()));
+        typed(&mut field, "there");
+        with_cursor(&field).must_be("hello there|");
+        field.undo();
+        with_cursor(&field).must_be("hello |world");
+    }
+
+    #[test]
+    fn plain_arrows_collapse_the_selection() {
+        let mut field = field("abcd");
+        field.move_to(1);
+        field.key(&shift(KeyCode::Right));
+        field.key(&shift(KeyCode::Right));
+        field.key(&KeyEvent::new(KeyCode::Left, KeyModifiers::NONE));
+        with_cursor(&field).must_be("a|bcd");
+        field.selection().must_be(None);
+        field.key(&shift(KeyCode::End));
+        field.key(&KeyEvent::new(KeyCode::Right, KeyModifiers::NONE));
+        with_cursor(&field).must_be("abcd|");
+    }
+
+    #[test]
+    fn cut_and_paste_go_through_the_register() {
+        let mut field = field("one two");
+        field.key(&ctrl(KeyCode::Char('w')));
+        field.key(&shift(KeyCode::Home));
+        field.action(CUT);
+        with_cursor(&field).must_be("|");
+        clipboard::contents().must_be("one ");
+        field.action(PASTE);
+        field.action(PASTE);
+        with_cursor(&field).must_be("one one |");
+        field.action(SELECT_ALL);
+        field.action(COPY);
+        clipboard::contents().must_be("one one ");
+    }
+
+    #[test]
+    fn pastes_are_one_edit() {
+        let (mut harness, buffer) = mount("");
+        harness.type_str("a").paste("b\r\nc").type_str("d");
+        buffer.borrow().clone().must_be("ab\ncd");
+        harness.press_with(KeyModifiers::CONTROL, KeyCode::Char('z'));
+        harness.press_with(KeyModifiers::CONTROL, KeyCode::Char('z'));
+        buffer.borrow().clone().must_be("a");
+    }
+
+    #[test]
+    fn dragging_selects() {
+        let (mut harness, _) = mount("hello");
+        harness
+            .mouse(
+                MouseEventKind::Down(MouseButton::Left),
+                Point { x: 1, y: 0 },
+            )
+            .mouse(
+                MouseEventKind::Drag(MouseButton::Left),
+                Point { x: 3, y: 0 },
+            )
+            .mouse(MouseEventKind::Up(MouseButton::Left), Point { x: 3, y: 0 });
+        let reversed = |x| {
+            harness
+                .cell(Point { x, y: 0 })
+                .and_then(|token| token.style())
+                .is_some_and(|style| style.reverse)
+        };
+        [0, 1, 2, 3]
+            .map(reversed)
+            .must_be([false, true, true, false]);
+    }
+
+    #[test]
+    fn editing_actions_are_bound_to_keys() {
+        let (mut harness, buffer) = mount("");
+        harness
+            .type_str("ab")
+            .press_with(KeyModifiers::CONTROL, KeyCode::Char('z'));
+        buffer.borrow().clone().must_be("");
+        harness.press_with(
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+            KeyCode::Char('Z'),
+        );
+        buffer.borrow().clone().must_be("ab");
+        harness
+            .press_with(KeyModifiers::CONTROL, KeyCode::Char('a'))
+            .press_with(KeyModifiers::SHIFT, KeyCode::Delete)
+            .press_with(KeyModifiers::SHIFT, KeyCode::Insert)
+            .press_with(KeyModifiers::SHIFT, KeyCode::Insert);
+        buffer.borrow().clone().must_be("abab");
+    }
+}